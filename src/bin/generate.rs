@@ -0,0 +1,298 @@
+#![allow(clippy::needless_return)]
+
+use moonlight_level_generation::{Generator, GeneratorConfig, LevelStats};
+use moonlight_level_generation::export::tiled;
+use serde::Serialize;
+use std::fs;
+use std::ops::Range;
+use std::cell::{Cell, RefCell};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+const USAGE: &str = "usage: generate --seeds <seed | start..end> [--out <dir>] [--png] [--scale <tile size>] [--json] [--svg] [--tiled] [--stats] [--config <file.toml | file.json>] [--threads <count>]";
+
+struct Options {
+    seeds: Range<u64>,
+    out: PathBuf,
+    png: bool,
+    png_scale: u32,
+    json: bool,
+    svg: bool,
+    tiled: bool,
+    stats: bool,
+    config: GeneratorConfig,
+    threads: usize,
+}
+
+#[derive(Serialize)]
+struct SeedReport {
+    seed: u64,
+    milliseconds: u128,
+    error: Option<String>,
+    stats: Option<LevelStats>,
+}
+
+thread_local! {
+    // set while a seed is being generated and written
+    static IN_SEED: Cell<bool> = const { Cell::new(false) };
+    // the message and location of the last panic in a seed on this thread
+    static SEED_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// generates every seed in the range without ever opening a window
+// a seed that panics is recorded in the report instead of stopping the run
+fn main() {
+    let options = parse_options();
+
+    if let Err(error) = fs::create_dir_all(&options.out) {
+        eprintln!("Could not create {:?}: {}", options.out, error);
+        std::process::exit(1);
+    }
+
+    // every map shares one tileset image, writing it from each thread would race
+    if options.tiled {
+        let path = options.out.join(tiled::TILESET_IMAGE);
+        if let Err(error) = tiled::write_tileset_image(&path) {
+            eprintln!("Could not write {:?}: {}", path, error);
+            std::process::exit(1);
+        }
+    }
+
+    // a panic in a seed goes in its report instead of being printed, any other panic is printed as usual
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if IN_SEED.with(Cell::get) {
+            SEED_PANIC.with(|panic| *panic.borrow_mut() = Some(info.to_string()));
+        } else {
+            default_hook(info);
+        }
+    }));
+
+    let options = Arc::new(options);
+    let next_seed = Arc::new(AtomicU64::new(options.seeds.start));
+    let reports = Arc::new(Mutex::new(Vec::new()));
+
+    let mut handles = Vec::new();
+    for _ in 0..options.threads {
+        let options = Arc::clone(&options);
+        let next_seed = Arc::clone(&next_seed);
+        let reports = Arc::clone(&reports);
+
+        handles.push(thread::spawn(move || {
+            loop {
+                let seed = next_seed.fetch_add(1, Ordering::SeqCst);
+                if seed >= options.seeds.end {
+                    break;
+                }
+
+                let report = generate_seed(&options, seed);
+                match &report.error {
+                    Some(error) => println!("seed {} failed after {}ms: {}", seed, report.milliseconds, error),
+                    None => println!("seed {} generated in {}ms", seed, report.milliseconds),
+                }
+
+                // written as soon as the seed is done so a run that is stopped early keeps what it finished
+                if options.stats {
+                    write_json(&options.out.join(format!("{}.stats.json", seed)), &report);
+                }
+
+                reports.lock().unwrap().push(report);
+            }
+        }));
+    }
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let mut reports = reports.lock().unwrap();
+    reports.sort_by_key(|report| report.seed);
+
+    let failed: Vec<u64> = reports.iter().filter(|report| report.error.is_some()).map(|report| report.seed).collect();
+    println!("{} of {} seeds failed {:?}", failed.len(), reports.len(), failed);
+
+    if options.stats && !write_json(&options.out.join("stats.json"), &*reports) {
+        std::process::exit(1);
+    }
+
+    if !failed.is_empty() {
+        std::process::exit(2);
+    }
+}
+
+// generation and the exports both run inside the catch, a panic in either only fails this seed
+fn generate_seed(options: &Options, seed: u64) -> SeedReport {
+    let start = Instant::now();
+
+    IN_SEED.with(|in_seed| in_seed.set(true));
+    let result = panic::catch_unwind(|| generate_and_write(options, seed, start));
+    IN_SEED.with(|in_seed| in_seed.set(false));
+
+    return match result {
+        Ok(report) => report,
+        Err(error) => SeedReport {
+            seed,
+            milliseconds: start.elapsed().as_millis(),
+            error: Some(SEED_PANIC.with(|panic| panic.borrow_mut().take()).unwrap_or_else(|| panic_message(error))),
+            stats: None,
+        },
+    };
+}
+
+fn generate_and_write(options: &Options, seed: u64, start: Instant) -> SeedReport {
+    let mut generator = Generator::with_config(seed, options.config);
+    let level = generator.generate();
+    let milliseconds = start.elapsed().as_millis();
+
+    // every failed write is reported, not just the last one
+    let mut errors = Vec::new();
+    if options.png {
+        let path = options.out.join(format!("{}.png", seed));
+        if let Err(write_error) = level.write_png(&path, options.png_scale) {
+            errors.push(format!("could not write {:?}: {}", path, write_error));
+        }
+    }
+
+    if options.json {
+        let path = options.out.join(format!("{}.json", seed));
+        let result = level.to_json()
+            .map_err(|json_error| json_error.to_string())
+            .and_then(|json| fs::write(&path, json).map_err(|io_error| io_error.to_string()));
+        if let Err(write_error) = result {
+            errors.push(format!("could not write {:?}: {}", path, write_error));
+        }
+    }
+
+    if options.svg {
+        let path = options.out.join(format!("{}.svg", seed));
+        if let Err(write_error) = level.write_svg(&path) {
+            errors.push(format!("could not write {:?}: {}", path, write_error));
+        }
+    }
+
+    if options.tiled {
+        let path = options.out.join(format!("{}.tmx", seed));
+        if let Err(write_error) = level.write_tiled_map(&path) {
+            errors.push(format!("could not write {:?}: {}", path, write_error));
+        }
+    }
+
+    return SeedReport {
+        seed,
+        milliseconds,
+        error: if errors.is_empty() {None} else {Some(errors.join(", "))},
+        stats: Some(level.get_stats()),
+    };
+}
+
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> bool {
+    let result = serde_json::to_string_pretty(value)
+        .map_err(|json_error| json_error.to_string())
+        .and_then(|json| fs::write(path, json).map_err(|io_error| io_error.to_string()));
+    if let Err(error) = result {
+        eprintln!("Could not write {:?}: {}", path, error);
+        return false;
+    }
+
+    return true;
+}
+
+fn panic_message(error: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = error.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    if let Some(message) = error.downcast_ref::<String>() {
+        return message.clone();
+    }
+
+    return "unknown panic".to_string();
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        seeds: 0..0,
+        out: PathBuf::from("."),
+        png: false,
+        png_scale: 1,
+        json: false,
+        svg: false,
+        tiled: false,
+        stats: false,
+        config: GeneratorConfig::default(),
+        threads: thread::available_parallelism().map(|count| count.get()).unwrap_or(1),
+    };
+
+    let mut has_seeds = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seeds" | "--seed" => {
+                options.seeds = parse_seeds(&args.next().unwrap_or_default());
+                has_seeds = true;
+            },
+            "--out" => options.out = PathBuf::from(args.next().unwrap_or_else(|| exit_with_usage("--out expects a directory"))),
+            "--png" => options.png = true,
+            "--scale" => options.png_scale = parse_arg(args.next(), "--scale expects an unsigned integer"),
+            "--json" => options.json = true,
+            "--svg" => options.svg = true,
+            "--tiled" => options.tiled = true,
+            "--stats" => options.stats = true,
+            "--config" => options.config = load_config(&args.next().unwrap_or_else(|| exit_with_usage("--config expects a path"))),
+            "--threads" => options.threads = parse_arg::<usize>(args.next(), "--threads expects an unsigned integer").max(1),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            },
+            _ => exit_with_usage(&format!("unknown argument {:?}", arg)),
+        }
+    }
+
+    if !has_seeds {
+        exit_with_usage("--seeds is required");
+    }
+
+    return options;
+}
+
+// accepts a single seed or an exclusive range like 0..1000
+fn parse_seeds(arg: &str) -> Range<u64> {
+    if let Some((start, end)) = arg.split_once("..") {
+        let start = parse_arg(Some(start.to_string()), "seed range start must be an unsigned integer");
+        let end = parse_arg(Some(end.to_string()), "seed range end must be an unsigned integer");
+
+        return start..end;
+    }
+
+    let seed: u64 = parse_arg(Some(arg.to_string()), "--seeds expects a seed or a range like 0..1000");
+    if seed == u64::MAX {
+        exit_with_usage("seed is too large to be used as a range");
+    }
+
+    return seed..(seed + 1);
+}
+
+fn load_config(path: &str) -> GeneratorConfig {
+    let source = fs::read_to_string(path).unwrap_or_else(|error| exit_with_usage(&format!("could not read {:?}: {}", path, error)));
+
+    let result = if Path::new(path).extension().and_then(|extension| extension.to_str()) == Some("json") {
+        GeneratorConfig::from_json(&source).map_err(|error| error.to_string())
+    } else {
+        GeneratorConfig::from_toml(&source).map_err(|error| error.to_string())
+    };
+
+    return result.unwrap_or_else(|error| exit_with_usage(&format!("invalid config {:?}: {}", path, error)));
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: Option<String>, message: &str) -> T {
+    return arg.and_then(|arg| arg.parse().ok()).unwrap_or_else(|| exit_with_usage(message));
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    std::process::exit(1);
+}
@@ -1,37 +1,61 @@
+#![allow(clippy::needless_return)]
+
 use winit::event_loop::{EventLoop, ControlFlow};
 use winit_input_helper::WinitInputHelper;
 use winit::window::{Window, WindowBuilder};
 use winit::dpi::{PhysicalSize, LogicalSize, LogicalPosition};
 use pixels::{SurfaceTexture, Pixels};
-use crate::generator::Generator;
 use winit::event::{Event, VirtualKeyCode};
+use moonlight_level_generation::{Generator, Level, SCREEN_WIDTH, SCREEN_HEIGHT};
 
-mod generator;
-mod random;
-mod perlin;
-mod math_helper;
-mod helpers;
-
-pub const SCREEN_WIDTH: u32 = 1920;
-pub const SCREEN_HEIGHT: u32 = 1080;
+const TITLE: &str = "Moonlight Level Generation";
 
+// usage: viewer [seed] [--png <path>] [--scale <tile size>]
+// R regenerates the current seed, shift + R rolls a new random seed
+// passing --png writes the map to disk and exits without opening a window
 fn main() {
     env_logger::init();
 
+    let mut seed: Option<u64> = None;
+    let mut png_path: Option<String> = None;
+    let mut png_scale: u32 = 1;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--png" => png_path = Some(args.next().unwrap_or_else(|| exit_with_usage("--png expects a path"))),
+            "--scale" => png_scale = parse_arg(args.next(), "--scale expects an unsigned integer"),
+            _ => seed = Some(parse_arg(Some(arg), "seed must be an unsigned integer")),
+        }
+    }
+
+    let mut seed = seed.unwrap_or_else(rand::random::<u64>);
+
+    if let Some(png_path) = png_path {
+        let mut generator = Generator::with_seed(seed);
+        let level = generator.generate();
+        if let Err(error) = level.write_png(&png_path, png_scale) {
+            eprintln!("Could not write {:?}: {}", png_path, error);
+            std::process::exit(1);
+        }
+
+        println!("SEED {:?} written to {:?}", seed, png_path);
+        return;
+    }
+
     let event_loop = EventLoop::new();
     let mut input_helper = WinitInputHelper::new();
     let (window, width, height) =
-        create_window("Moonlight Level Generation", &event_loop);
+        create_window(TITLE, &event_loop);
 
     let surface_texture = SurfaceTexture::new(width, height, &window);
 
     let mut pixels = Pixels::new(SCREEN_WIDTH, SCREEN_HEIGHT, surface_texture).unwrap();
-    // let mut generator = Generator::default();
+    let mut level = generate_level(&window, seed);
 
-    event_loop.run(move |event, target, control_flow| {
+    event_loop.run(move |event, _, control_flow| {
         if let Event::RedrawRequested(_) = event {
-            let mut generator = Generator::default();
-            generator.render(pixels.get_frame());
+            level.render(pixels.get_frame(), SCREEN_WIDTH, SCREEN_HEIGHT);
 
             if pixels.render().is_err() {
                 *control_flow = ControlFlow::Exit;
@@ -46,6 +70,11 @@ fn main() {
             }
 
             if input_helper.key_pressed(VirtualKeyCode::R) {
+                if input_helper.held_shift() {
+                    seed = rand::random::<u64>();
+                }
+
+                level = generate_level(&window, seed);
                 window.request_redraw();
             }
 
@@ -58,11 +87,30 @@ fn main() {
     });
 }
 
+fn parse_arg<T: std::str::FromStr>(arg: Option<String>, message: &str) -> T {
+    return arg.and_then(|arg| arg.parse().ok()).unwrap_or_else(|| exit_with_usage(message));
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("usage: viewer [seed] [--png <path>] [--scale <tile size>]");
+    std::process::exit(1);
+}
+
+fn generate_level(window: &Window, seed: u64) -> Level {
+    println!("SEED {:?}", seed);
+    window.set_title(&format!("{} - seed {}", TITLE, seed));
+
+    let mut generator = Generator::with_seed(seed);
+
+    return generator.generate();
+}
+
 fn create_window(title: &str, event_loop: &EventLoop<()>) -> (Window, u32, u32) {
     let window = WindowBuilder::new()
         .with_visible(false)
         .with_title(title)
-        .build(&event_loop)
+        .build(event_loop)
         .unwrap();
 
     let hidpi_factor = window.scale_factor();
@@ -0,0 +1,131 @@
+use line_drawing::Bresenham;
+use crate::math_helper::MathHelper;
+use crate::generator::{TEXTURE_WIDTH, TEXTURE_HEIGHT};
+use crate::level::SafeZone;
+
+pub const BACKGROUND: u32 = 0x000000ff;
+
+// a tile sized pixel buffer centered on the origin, one pixel per tile
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+    // thick lines are never drawn inside the safe zone
+    safe_zone: SafeZone,
+}
+
+impl Canvas {
+    pub fn create(width: usize, height: usize, safe_zone: SafeZone) -> Self {
+        return Canvas {
+            width,
+            height,
+            pixels: vec![BACKGROUND; width * height],
+            safe_zone,
+        };
+    }
+
+    pub fn draw_tile(&mut self, x: i32, y: i32, color: u32, alpha: f32) {
+        let center_x = (self.width / 2) as i32;
+        let center_y = (self.height / 2) as i32;
+
+        // dont draw outside bounds, itll crash at casting
+        if (x + center_x) < 0 || (y + center_y) < 0 {
+            return;
+        }
+
+        let x = (center_x + x) as u32;
+        let y = (center_y + y) as u32;
+
+        self.draw_rect(x, y, 1, 1, color, alpha);
+    }
+
+    pub fn draw_oval(&mut self, center_x: f32, center_y: f32, width: f32, height: f32, color: u32, alpha: f32) {
+        let center_x = (center_x / TEXTURE_WIDTH as f32) as i32;
+        let center_y = (center_y / TEXTURE_HEIGHT as f32) as i32;
+
+        let width = (width / TEXTURE_WIDTH as f32) as i32;
+        let height = (height / TEXTURE_HEIGHT as f32) as i32;
+
+        for x in (center_x - width / 2)..(center_x + width / 2) {
+            for y in (center_y - height / 2)..(center_y + height / 2) {
+                let dx = (x - center_x) as f32 / (width as f32 / 2.0);
+                let dy = (y - center_y) as f32 / (height as f32 / 2.0);
+                let d2 = dx * dx + dy * dy;
+
+                if d2 <= 1.0 {
+                    self.draw_tile(x, y, color, alpha);
+                }
+            }
+        }
+    }
+
+    pub fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, color: u32, alpha: f32) {
+        let x1 = (x1 / TEXTURE_WIDTH as f32) as i32;
+        let y1 = (y1 / TEXTURE_HEIGHT as f32) as i32;
+        let x2 = (x2 / TEXTURE_WIDTH as f32) as i32;
+        let y2 = (y2 / TEXTURE_HEIGHT as f32) as i32;
+
+        for (x, y) in Bresenham::new((x1, y1), (x2, y2)) {
+            self.draw_tile(x, y, color, alpha);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_line_thickness(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32, color: u32, alpha: f32) {
+        let aabb = [
+            [((x1.min(x2) - radius) / TEXTURE_WIDTH as f32).floor() as i32, ((y1.min(y2) - radius) / TEXTURE_HEIGHT as f32).floor() as i32],
+            [((x1.max(x2) + radius) / TEXTURE_WIDTH as f32).ceil() as i32, ((y1.max(y2) + radius) / TEXTURE_HEIGHT as f32).ceil() as i32],
+        ];
+
+        for x in aabb[0][0]..=aabb[1][0] {
+            for y in aabb[0][1]..=aabb[1][1] {
+                let cx = x as f32 * TEXTURE_WIDTH as f32 + TEXTURE_WIDTH as f32 / 2.0;
+                let cy = y as f32 * TEXTURE_HEIGHT as f32 + TEXTURE_HEIGHT as f32 / 2.0;
+
+                if MathHelper::is_point_inside_ellipse([cx, cy], self.safe_zone.center, [self.safe_zone.width - TEXTURE_WIDTH as f32 * 2.0, self.safe_zone.height - TEXTURE_HEIGHT as f32 * 2.0]) {
+                    continue;
+                }
+
+                let (_point, distance) = MathHelper::distance_to_line_segment(&[[x1, y1], [x2, y2]], &[cx, cy]);
+                if distance - 0.000001 <= radius {
+                    self.draw_tile(x, y, color, alpha);
+                }
+            }
+        }
+    }
+
+    fn draw_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: u32, alpha: f32) {
+        for offset_y in 0..height {
+            let y = y + offset_y;
+
+            for offset_x in 0..width {
+                let x = x + offset_x;
+
+                if let Some(index) = self.grid_index(x, y) {
+                    let orig_color_r = (self.pixels[index] & 0xff000000) >> 24;
+                    let orig_color_g = (self.pixels[index] & 0x00ff0000) >> 16;
+                    let orig_color_b = (self.pixels[index] & 0x0000ff00) >> 8;
+
+                    let color_r = (color & 0xff0000) >> 16;
+                    let color_g = (color & 0x00ff00) >> 8;
+                    let color_b = color & 0x0000ff;
+
+                    let new_color_r = (orig_color_r as f32 * (1.0 - alpha) + color_r as f32 * alpha).round() as u32;
+                    let new_color_g = (orig_color_g as f32 * (1.0 - alpha) + color_g as f32 * alpha).round() as u32;
+                    let new_color_b = (orig_color_b as f32 * (1.0 - alpha) + color_b as f32 * alpha).round() as u32;
+
+                    self.pixels[index] = (new_color_r << 24) | (new_color_g << 16) | (new_color_b << 8) | 0x000000ff;
+                }
+            }
+        }
+    }
+
+    fn grid_index(&self, x: u32, y: u32) -> Option<usize> {
+        let index = y * self.width as u32 + x;
+        if index >= (self.width * self.height) as u32 {
+            return None;
+        }
+
+        return Some(index as usize);
+    }
+}
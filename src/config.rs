@@ -0,0 +1,180 @@
+use serde::{Serialize, Deserialize};
+use crate::fractal_noise::FractalNoise;
+use crate::domain_warp::DomainWarp;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoadWallMode {
+    // stop just short of the wall
+    Terminate,
+    // turn to run along the wall, stopping if that still runs into one
+    Deflect,
+    // go through the nearest path in the wall, deflecting when there isn't a clear one close enough
+    RouteToPath,
+}
+
+// every tunable number the generator uses, missing fields in a config file fall back to the defaults
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneratorConfig {
+    pub noise_detail: f32,
+
+    // the safe zone is the base width and height multiplied by the scale
+    pub safe_zone_width: f32,
+    pub safe_zone_height: f32,
+    pub safe_zone_scale: f32,
+
+    // divider walls
+    pub wall_length: f32,
+    pub wall_start_thickness: f32,
+    pub wall_end_thickness: f32,
+
+    // path gaps cut into the walls are min + range * random
+    pub path_min_thickness: f32,
+    pub path_thickness_range: f32,
+
+    // hermite tangent strengths for the back walls
+    pub t1_back_wall_tangent_strength: f32,
+    pub t2_back_wall_tangent_strength: f32,
+    pub t3_side_wall_tangent_strength: f32,
+
+    pub road_length: f32,
+    // how far the roads planted through the wall paths carry on past them
+    pub path_road_length: f32,
+
+    // cleanup once the roads are grown, zero turns a step off
+    // roads closer than the merge distance and within the merge angle in degrees of each other are merged,
+    // loose ends within the snap distance are joined onto the nearest road,
+    // and dead ends shorter than the dead end length are extended into a road ahead of them or removed
+    pub road_merge_distance: f32,
+    pub road_merge_angle: f32,
+    pub road_snap_distance: f32,
+    pub road_dead_end_length: f32,
+
+    // what roads do when they grow into a wall, and how far they look for a path to route through
+    pub road_wall_mode: RoadWallMode,
+    pub road_path_search_distance: f32,
+
+    // the noise behind each feature, all a single octave of perlin by default
+    // these stay last so they serialize as toml tables after the plain values
+    pub road_tile_noise: FractalNoise,
+    pub road_drift_noise: FractalNoise,
+    pub road_thickness_noise: FractalNoise,
+    pub wall_drift_noise: FractalNoise,
+    pub wall_wobble_noise: FractalNoise,
+    pub wall_thickness_noise: FractalNoise,
+
+    // domain warping on top of the noise above, off by default
+    pub road_drift_warp: DomainWarp,
+    pub wall_wobble_warp: DomainWarp,
+}
+
+impl GeneratorConfig {
+    pub fn get_safe_zone_width(&self) -> f32 {
+        return self.safe_zone_width * self.safe_zone_scale;
+    }
+
+    pub fn get_safe_zone_height(&self) -> f32 {
+        return self.safe_zone_height * self.safe_zone_scale;
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, toml::de::Error> {
+        return toml::from_str(source);
+    }
+
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        return toml::to_string_pretty(self);
+    }
+
+    pub fn from_json(source: &str) -> Result<Self, serde_json::Error> {
+        return serde_json::from_str(source);
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        return serde_json::to_string_pretty(self);
+    }
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        return GeneratorConfig {
+            noise_detail: 0.0005 / 0.75,
+            safe_zone_width: 3072.0,
+            safe_zone_height: 2304.0,
+            safe_zone_scale: 2.0,
+            wall_length: 45000.0,
+            wall_start_thickness: 200.0,
+            wall_end_thickness: 1200.0,
+            path_min_thickness: 900.0,
+            path_thickness_range: 1400.0,
+            t1_back_wall_tangent_strength: 24000.0,
+            t2_back_wall_tangent_strength: 54000.0,
+            t3_side_wall_tangent_strength: 8000.0,
+            road_length: 40000.0,
+            path_road_length: 15000.0,
+            road_merge_distance: 200.0,
+            road_merge_angle: 20.0,
+            road_snap_distance: 600.0,
+            road_dead_end_length: 1500.0,
+            road_wall_mode: RoadWallMode::RouteToPath,
+            road_path_search_distance: 10000.0,
+            road_tile_noise: FractalNoise::default(),
+            road_drift_noise: FractalNoise::default(),
+            road_thickness_noise: FractalNoise::default(),
+            wall_drift_noise: FractalNoise::default(),
+            wall_wobble_noise: FractalNoise::default(),
+            wall_thickness_noise: FractalNoise::default(),
+            road_drift_warp: DomainWarp::default(),
+            wall_wobble_warp: DomainWarp::default(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal_noise::FractalMode;
+
+    #[test]
+    fn toml_round_trip() {
+        let default = GeneratorConfig::default();
+        let config = GeneratorConfig {
+            road_length: 1234.0,
+            road_wall_mode: RoadWallMode::Deflect,
+            wall_drift_noise: FractalNoise {mode: FractalMode::Ridged, ..default.wall_drift_noise},
+            road_drift_warp: DomainWarp {strength: 2.5, ..default.road_drift_warp},
+            ..default
+        };
+
+        let source = config.to_toml().unwrap();
+        assert_eq!(GeneratorConfig::from_toml(&source).unwrap(), config);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let default = GeneratorConfig::default();
+        let config = GeneratorConfig {
+            safe_zone_scale: 3.0,
+            road_tile_noise: FractalNoise {octaves: 4, ..default.road_tile_noise},
+            ..default
+        };
+
+        let source = config.to_json().unwrap();
+        assert_eq!(GeneratorConfig::from_json(&source).unwrap(), config);
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let config = GeneratorConfig::from_toml("road_length = 500.0\n").unwrap();
+        assert_eq!(config.road_length, 500.0);
+        assert_eq!(config.wall_length, GeneratorConfig::default().wall_length);
+        assert_eq!(config.road_drift_noise, GeneratorConfig::default().road_drift_noise);
+    }
+
+    #[test]
+    fn safe_zone_is_scaled() {
+        let config = GeneratorConfig::default();
+        assert_eq!(config.get_safe_zone_width(), config.safe_zone_width * config.safe_zone_scale);
+        assert_eq!(config.get_safe_zone_height(), config.safe_zone_height * config.safe_zone_scale);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::fractal_noise::FractalNoise;
+use crate::perlin::Perlin;
+use serde::{Serialize, Deserialize};
+
+// pushes the coordinates around by two more noise fields before the real noise gets sampled
+// the x and y offsets each come from their own independently seeded field, so they don't correlate
+// with more iterations the warp itself gets warped, which is what gives the eroded look
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DomainWarp {
+    // how far the coordinates can move in noise space, zero turns warping off
+    pub strength: f32,
+    // frequency of the warp fields relative to the noise being warped
+    pub frequency: f32,
+    pub iterations: u32,
+    pub noise: FractalNoise,
+}
+
+impl DomainWarp {
+    pub fn warp(&self, fields: &[Perlin; 2], x: f32, y: f32) -> [f32; 2] {
+        if self.strength == 0.0 {
+            return [x, y];
+        }
+
+        let mut offset = [0.0, 0.0];
+        for _iteration in 0..self.iterations {
+            let warp_x = (x + offset[0]) * self.frequency;
+            let warp_y = (y + offset[1]) * self.frequency;
+
+            offset = [
+                self.noise.sample(&fields[0], warp_x, warp_y) * self.strength,
+                self.noise.sample(&fields[1], warp_x, warp_y) * self.strength,
+            ];
+        }
+
+        return [x + offset[0], y + offset[1]];
+    }
+
+    pub fn sample(&self, noise: &Perlin, fields: &[Perlin; 2], fractal: &FractalNoise, x: f32, y: f32) -> f32 {
+        let point = self.warp(fields, x, y);
+        return fractal.sample(noise, point[0], point[1]);
+    }
+}
+
+impl Default for DomainWarp {
+    fn default() -> Self {
+        return DomainWarp {
+            strength: 0.0,
+            frequency: 1.0,
+            iterations: 2,
+            noise: FractalNoise {
+                octaves: 3,
+                ..FractalNoise::default()
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> [Perlin; 2] {
+        return [Perlin::create(1), Perlin::create(2)];
+    }
+
+    #[test]
+    fn no_strength_is_no_warp() {
+        let warp = DomainWarp::default();
+        assert_eq!(warp.warp(&fields(), 1.25, -3.5), [1.25, -3.5]);
+
+        let noise = Perlin::create(3);
+        let fractal = FractalNoise::default();
+        assert_eq!(warp.sample(&noise, &fields(), &fractal, 1.25, -3.5), fractal.sample(&noise, 1.25, -3.5));
+    }
+
+    #[test]
+    fn offsets_come_from_separate_fields() {
+        let warp = DomainWarp {
+            strength: 1.0,
+            iterations: 1,
+            ..DomainWarp::default()
+        };
+
+        // with one iteration each offset is just its own field sampled at the point
+        let fields = fields();
+        let point = warp.warp(&fields, 0.3, 0.7);
+        assert_eq!(point, [0.3 + warp.noise.sample(&fields[0], 0.3, 0.7), 0.7 + warp.noise.sample(&fields[1], 0.3, 0.7)]);
+        assert_ne!(point[0] - 0.3, point[1] - 0.7);
+    }
+
+    #[test]
+    fn warp_is_bounded_by_the_strength() {
+        let warp = DomainWarp {
+            strength: 0.5,
+            ..DomainWarp::default()
+        };
+
+        for index in 0..100 {
+            let x = index as f32 * 0.173;
+            let y = index as f32 * -0.291;
+            let point = warp.warp(&fields(), x, y);
+            assert!((point[0] - x).abs() <= 0.5 && (point[1] - y).abs() <= 0.5);
+        }
+    }
+}
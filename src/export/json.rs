@@ -0,0 +1,307 @@
+use crate::level::{Level, LevelWalls, LevelZones, SafeZone, Tile, Zone};
+use crate::generator::{TEXTURE_WIDTH, TEXTURE_HEIGHT};
+use crate::helpers::wall_section::WallSection;
+use crate::helpers::road_segment::{RoadSegment, Point};
+use crate::helpers::zone_polygon::ZonePolygon;
+use std::sync::OnceLock;
+use serde::{Serialize, Deserialize};
+use serde::de::Error;
+use std::collections::HashMap;
+
+// bump this whenever a field is renamed, removed or changes meaning
+// adding new optional fields does not need a new version
+pub const SCHEMA_VERSION: u32 = 2;
+
+const GRASS: char = '.';
+const GRAVEL: char = '#';
+
+#[derive(Serialize, Deserialize)]
+struct LevelFile {
+    version: u32,
+    seed: u64,
+    safe_zone: SafeZone,
+    walls: Vec<WallFile>,
+    zones: Vec<ZoneFile>,
+    roads: Vec<RoadFile>,
+    tiles: TileMapFile,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WallFile {
+    name: String,
+    points: Vec<[f32; 2]>,
+}
+
+// closed polygons, the last point connects back to the first
+#[derive(Serialize, Deserialize)]
+struct ZoneFile {
+    name: String,
+    points: Vec<[f32; 2]>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RoadFile {
+    points: Vec<Point>,
+}
+
+// one string per row, top to bottom, where '.' is grass and '#' is gravel
+// the tile at [origin[0], origin[1]] is the tile containing world position (0, 0)
+#[derive(Serialize, Deserialize)]
+struct TileMapFile {
+    width: usize,
+    height: usize,
+    origin: [usize; 2],
+    tile_size: [u32; 2],
+    rows: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct VersionProbe {
+    version: u32,
+}
+
+pub fn to_json(level: &Level) -> Result<String, serde_json::Error> {
+    return serde_json::to_string(&to_level_file(level)?);
+}
+
+pub fn to_json_pretty(level: &Level) -> Result<String, serde_json::Error> {
+    return serde_json::to_string_pretty(&to_level_file(level)?);
+}
+
+pub fn from_json(source: &str) -> Result<Level, serde_json::Error> {
+    // check the version first so an old file gets a useful error instead of a missing field
+    let probe: VersionProbe = serde_json::from_str(source)?;
+    if probe.version != SCHEMA_VERSION {
+        return Err(serde_json::Error::custom(format!("unsupported level schema version {}, expected {}", probe.version, SCHEMA_VERSION)));
+    }
+
+    let file: LevelFile = serde_json::from_str(source)?;
+
+    return from_level_file(file);
+}
+
+fn to_level_file(level: &Level) -> Result<LevelFile, serde_json::Error> {
+    // json has no nan or infinity, serde_json would write them as null and the file couldn't be read back
+    let mut walls = Vec::new();
+    for (name, wall) in level.walls.iter() {
+        check_finite(name, wall.lines.iter().copied())?;
+        walls.push(WallFile {
+            name: name.to_string(),
+            points: wall.lines.clone(),
+        });
+    }
+
+    let mut zones = Vec::new();
+    for (zone, polygon) in level.zones.iter() {
+        check_finite(zone_name(zone), polygon.points.iter().copied())?;
+        zones.push(ZoneFile {
+            name: zone_name(zone).to_string(),
+            points: polygon.points.clone(),
+        });
+    }
+
+    let mut roads = Vec::new();
+    for (index, road) in level.roads.iter().enumerate() {
+        check_finite(&format!("road {}", index), road.points.iter().map(|point| [point.x, point.y, point.thickness]))?;
+        roads.push(RoadFile {
+            points: road.points.clone(),
+        });
+    }
+
+    let rows = level.tiles.chunks(level.width).map(|row| {
+        return row.iter().map(|tile| if *tile == Tile::Gravel {GRAVEL} else {GRASS}).collect();
+    }).collect();
+
+    return Ok(LevelFile {
+        version: SCHEMA_VERSION,
+        seed: level.seed,
+        safe_zone: level.safe_zone,
+        walls,
+        zones,
+        roads,
+        tiles: TileMapFile {
+            width: level.width,
+            height: level.height,
+            origin: [level.width / 2, level.height / 2],
+            tile_size: [TEXTURE_WIDTH, TEXTURE_HEIGHT],
+            rows,
+        },
+    });
+}
+
+fn check_finite<const N: usize>(name: &str, points: impl Iterator<Item = [f32; N]>) -> Result<(), serde_json::Error> {
+    for (index, point) in points.enumerate() {
+        if !point.iter().all(|value| value.is_finite()) {
+            return Err(serde_json::Error::custom(format!("{} has a non finite point {:?} at {}", name, point, index)));
+        }
+    }
+
+    return Ok(());
+}
+
+fn from_level_file(file: LevelFile) -> Result<Level, serde_json::Error> {
+    // a line needs two points and an area three, anything shorter can't be drawn or searched
+    let mut walls: HashMap<String, WallSection> = HashMap::new();
+    for wall in file.walls {
+        check_length(&wall.name, wall.points.len(), 2)?;
+        walls.insert(wall.name, WallSection {
+            lines: wall.points,
+        });
+    }
+
+    let mut take_wall = |name: &str| {
+        return walls.remove(name).ok_or_else(|| serde_json::Error::custom(format!("missing wall {:?}", name)));
+    };
+
+    let level_walls = LevelWalls {
+        t1_inner_1: take_wall("t1_inner_1")?,
+        t1_inner_2: take_wall("t1_inner_2")?,
+        t2_t1_inner: take_wall("t2_t1_inner")?,
+        t3_t2_inner: take_wall("t3_t2_inner")?,
+        outer_wall: take_wall("outer_wall")?,
+        t1_upper_back_wall: take_wall("t1_upper_back_wall")?,
+        t1_lower_back_wall: take_wall("t1_lower_back_wall")?,
+        t2_back_wall: take_wall("t2_back_wall")?,
+        t3_back_wall: take_wall("t3_back_wall")?,
+    };
+
+    let mut zones: HashMap<String, ZonePolygon> = HashMap::new();
+    for zone in file.zones {
+        check_length(&zone.name, zone.points.len(), 3)?;
+        zones.insert(zone.name, ZonePolygon {
+            points: zone.points,
+        });
+    }
+
+    let mut take_zone = |zone: Zone| {
+        let name = zone_name(zone);
+        return zones.remove(name).ok_or_else(|| serde_json::Error::custom(format!("missing zone {:?}", name)));
+    };
+
+    let level_zones = LevelZones {
+        safe_zone: take_zone(Zone::Safe)?,
+        t1: take_zone(Zone::T1)?,
+        t2: take_zone(Zone::T2)?,
+        t3: take_zone(Zone::T3)?,
+    };
+
+    let tile_map = file.tiles;
+    if tile_map.rows.len() != tile_map.height {
+        return Err(serde_json::Error::custom(format!("expected {} tile rows, found {}", tile_map.height, tile_map.rows.len())));
+    }
+
+    if tile_map.origin != [tile_map.width / 2, tile_map.height / 2] {
+        return Err(serde_json::Error::custom(format!("tile origin {:?} must be the center of the map", tile_map.origin)));
+    }
+
+    let mut tiles = Vec::with_capacity(tile_map.width * tile_map.height);
+    for (y, row) in tile_map.rows.iter().enumerate() {
+        if row.chars().count() != tile_map.width {
+            return Err(serde_json::Error::custom(format!("tile row {} is not {} tiles wide", y, tile_map.width)));
+        }
+
+        for tile in row.chars() {
+            match tile {
+                GRASS => tiles.push(Tile::Grass),
+                GRAVEL => tiles.push(Tile::Gravel),
+                _ => return Err(serde_json::Error::custom(format!("unknown tile {:?} in row {}", tile, y))),
+            }
+        }
+    }
+
+    let mut roads = Vec::new();
+    for (index, road) in file.roads.into_iter().enumerate() {
+        check_length(&format!("road {}", index), road.points.len(), 2)?;
+        roads.push(RoadSegment::from_points(road.points));
+    }
+
+    return Ok(Level {
+        seed: file.seed,
+        width: tile_map.width,
+        height: tile_map.height,
+        tiles,
+        safe_zone: file.safe_zone,
+        walls: level_walls,
+        zones: level_zones,
+        roads,
+        index: OnceLock::new(),
+        road_network: OnceLock::new(),
+    });
+}
+
+fn check_length(name: &str, length: usize, minimum: usize) -> Result<(), serde_json::Error> {
+    if length < minimum {
+        return Err(serde_json::Error::custom(format!("{} has {} points, it needs at least {}", name, length, minimum)));
+    }
+
+    return Ok(());
+}
+
+fn zone_name(zone: Zone) -> &'static str {
+    return match zone {
+        Zone::Safe => "safe_zone",
+        Zone::T1 => "t1",
+        Zone::T2 => "t2",
+        Zone::T3 => "t3",
+        Zone::Outside => "outside",
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for seed in [0, 1, 7, 42, 138] {
+            let level = crate::generate(seed);
+            let source = to_json(&level).unwrap();
+            let loaded = from_json(&source).unwrap();
+
+            assert_eq!(loaded.seed, seed);
+            assert_eq!(loaded.tiles, level.tiles);
+            assert_eq!(loaded.safe_zone, level.safe_zone);
+            for ((name, wall), (_name, loaded_wall)) in level.walls.iter().into_iter().zip(loaded.walls.iter()) {
+                assert_eq!(wall.lines, loaded_wall.lines, "{} changed in seed {}", name, seed);
+            }
+
+            assert_eq!(loaded.roads.len(), level.roads.len());
+            for (road, loaded_road) in level.roads.iter().zip(&loaded.roads) {
+                assert_eq!(road.points, loaded_road.points);
+            }
+
+            assert_eq!(to_json(&loaded).unwrap(), source);
+        }
+    }
+
+    #[test]
+    fn rejects_non_finite_points() {
+        let mut level = crate::generate(0);
+        level.roads[0].points[0].x = f32::NAN;
+
+        assert!(to_json(&level).is_err());
+        assert!(to_json_pretty(&level).is_err());
+    }
+
+    #[test]
+    fn rejects_short_polylines() {
+        let mut level = crate::generate(0);
+        level.roads[0].points.truncate(1);
+        // rendering them is fine, they just draw nothing
+        level.render_pixels();
+
+        let error = from_json(&to_json(&level).unwrap()).err().unwrap();
+        assert_eq!(error.to_string(), "road 0 has 1 points, it needs at least 2");
+
+        let mut level = crate::generate(0);
+        level.walls.outer_wall.lines.clear();
+        level.render_pixels();
+        assert!(from_json(&to_json(&level).unwrap()).is_err());
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let source = to_json(&crate::generate(0)).unwrap().replacen(&format!("\"version\":{}", SCHEMA_VERSION), "\"version\":1", 1);
+        assert!(from_json(&source).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod json;
+pub mod svg;
+pub mod tiled;
\ No newline at end of file
@@ -0,0 +1,134 @@
+use crate::level::Level;
+use crate::generator::{TEXTURE_WIDTH, TEXTURE_HEIGHT};
+use crate::helpers::road_segment::Point;
+use std::fmt::Write;
+use std::path::Path;
+
+const BACKGROUND_COLOR: &str = "#43711d";
+const WALL_COLOR: &str = "#000000";
+const ROAD_COLOR: &str = "#ffffbb";
+const SAFE_ZONE_COLOR: &str = "#39a8e7";
+const T1_COLOR: &str = "#e7d439";
+const T2_COLOR: &str = "#e78a39";
+const T3_COLOR: &str = "#e73939";
+
+const WALL_STROKE_WIDTH: f32 = 64.0;
+
+pub fn write_svg<P: AsRef<Path>>(level: &Level, path: P) -> std::io::Result<()> {
+    return std::fs::write(path, to_svg(level));
+}
+
+// svg in world units, every layer is a group with the layer name as its id so they show up in vector editors
+pub fn to_svg(level: &Level) -> String {
+    let min_x = -((level.width / 2) as f32) * TEXTURE_WIDTH as f32;
+    let min_y = -((level.height / 2) as f32) * TEXTURE_HEIGHT as f32;
+    let width = level.width as f32 * TEXTURE_WIDTH as f32;
+    let height = level.height as f32 * TEXTURE_HEIGHT as f32;
+
+    let mut svg = String::new();
+    writeln!(svg, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}">"#, min_x, min_y, width, height, level.width, level.height).unwrap();
+    writeln!(svg, r#" <rect id="ground" x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#, min_x, min_y, width, height, BACKGROUND_COLOR).unwrap();
+
+    writeln!(svg, r#" <g id="roads" fill="{}" stroke="none">"#, ROAD_COLOR).unwrap();
+    for (index, road) in level.roads.iter().enumerate() {
+        if !road.is_drawable() {
+            continue;
+        }
+
+        writeln!(svg, r#"  <path id="road_{}" d="{}"/>"#, index, road_outline(&road.points)).unwrap();
+    }
+    writeln!(svg, r#" </g>"#).unwrap();
+
+    let safe_zone = level.safe_zone;
+    writeln!(svg, r#" <g id="zones">"#).unwrap();
+    for (id, polygon, color) in [("t1", &level.zones.t1, T1_COLOR), ("t2", &level.zones.t2, T2_COLOR), ("t3", &level.zones.t3, T3_COLOR)] {
+        writeln!(svg, r#"  <polygon id="{}" points="{}" fill="{}" fill-opacity="0.25"/>"#, id, format_points(&polygon.points), color).unwrap();
+    }
+    writeln!(
+        svg,
+        r#"  <ellipse id="safe_zone" cx="{}" cy="{}" rx="{}" ry="{}" fill="{}" fill-opacity="0.5"/>"#,
+        safe_zone.center[0], safe_zone.center[1], safe_zone.width / 2.0, safe_zone.height / 2.0, SAFE_ZONE_COLOR).unwrap();
+    writeln!(svg, r#" </g>"#).unwrap();
+
+    writeln!(svg, r#" <g id="walls" fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round">"#, WALL_COLOR, WALL_STROKE_WIDTH).unwrap();
+    for (name, wall) in level.walls.boundaries() {
+        writeln!(svg, r#"  <polyline id="{}" points="{}"/>"#, name, format_points(&wall.lines)).unwrap();
+    }
+    writeln!(svg, r#" </g>"#).unwrap();
+
+    // the back walls are already part of the outer wall, they're kept hidden for reference
+    writeln!(svg, r#" <g id="back_walls" fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round" visibility="hidden">"#, WALL_COLOR, WALL_STROKE_WIDTH).unwrap();
+    for (name, wall) in level.walls.back_walls() {
+        writeln!(svg, r#"  <polyline id="{}" points="{}"/>"#, name, format_points(&wall.lines)).unwrap();
+    }
+    writeln!(svg, r#" </g>"#).unwrap();
+
+    writeln!(svg, r#"</svg>"#).unwrap();
+
+    return svg;
+}
+
+fn format_points(points: &[[f32; 2]]) -> String {
+    return points.iter().map(|point| format!("{},{}", point[0], point[1])).collect::<Vec<String>>().join(" ");
+}
+
+// svg strokes can't change width along a path, so the road is drawn as a filled outline
+// offset by half the thickness on either side of every point
+fn road_outline(points: &[Point]) -> String {
+    let mut left = Vec::with_capacity(points.len());
+    let mut right = Vec::with_capacity(points.len());
+
+    for index in 0..points.len() {
+        let previous = &points[index.saturating_sub(1)];
+        let next = &points[(index + 1).min(points.len() - 1)];
+        let point = &points[index];
+
+        let angle = (next.y - previous.y).atan2(next.x - previous.x);
+        let normal = [-angle.sin() * point.thickness / 2.0, angle.cos() * point.thickness / 2.0];
+
+        left.push([point.x + normal[0], point.y + normal[1]]);
+        right.push([point.x - normal[0], point.y - normal[1]]);
+    }
+
+    let mut path = format!("M{},{}", left[0][0], left[0][1]);
+    for point in left.iter().skip(1) {
+        write!(path, " L{},{}", point[0], point[1]).unwrap();
+    }
+    for point in right.iter().rev() {
+        write!(path, " L{},{}", point[0], point[1]).unwrap();
+    }
+    path.push_str(" Z");
+
+    return path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers() {
+        let level = crate::generate(0);
+        let svg = to_svg(&level);
+
+        assert!(svg.starts_with("<?xml"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        for id in ["ground", "roads", "zones", "walls", "back_walls", "safe_zone", "t1", "t2", "t3"] {
+            assert!(svg.contains(&format!(r#"id="{}""#, id)), "missing {}", id);
+        }
+
+        assert_eq!(svg.matches("<polyline ").count(), level.walls.iter().len());
+        assert_eq!(svg.matches("<path ").count(), level.roads.iter().filter(|road| road.is_drawable()).count());
+    }
+
+    #[test]
+    fn road_outline_is_offset_by_half_the_thickness() {
+        let points = [
+            Point {x: 0.0, y: 0.0, thickness: 10.0},
+            Point {x: 100.0, y: 0.0, thickness: 20.0},
+        ];
+
+        assert_eq!(road_outline(&points), "M0,5 L100,10 L100,-10 L0,-5 Z");
+    }
+}
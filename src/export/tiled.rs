@@ -0,0 +1,371 @@
+use crate::level::{Level, Tile};
+use crate::canvas::{Canvas, BACKGROUND};
+use crate::generator::{TEXTURE_WIDTH, TEXTURE_HEIGHT};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub const TILESET_IMAGE: &str = "moonlight_tiles.png";
+
+// global tile ids, 0 is an empty tile in tiled
+const EMPTY_GID: u32 = 0;
+const GRASS_GID: u32 = 1;
+const GRAVEL_GID: u32 = 2;
+const SAFE_ZONE_GID: u32 = 3;
+
+// the tileset image colors, in gid order
+const TILESET_COLORS: [u32; 3] = [0x43711dff, 0xffffffff, 0x39a8e7ff];
+
+struct TiledMap {
+    width: usize,
+    height: usize,
+    tile_layers: Vec<TileLayer>,
+    object_groups: Vec<ObjectGroup>,
+}
+
+struct TileLayer {
+    name: &'static str,
+    data: Vec<u32>,
+}
+
+struct ObjectGroup {
+    name: &'static str,
+    objects: Vec<Polyline>,
+}
+
+// a polyline in map pixels, relative to its first point like tiled expects
+struct Polyline {
+    name: String,
+    class: &'static str,
+    position: [f32; 2],
+    points: Vec<[f32; 2]>,
+    widths: Option<Vec<f32>>,
+}
+
+// the map and the tileset image next to it so it opens without any setup
+pub fn write_tiled<P: AsRef<Path>>(level: &Level, path: P) -> std::io::Result<()> {
+    let path = path.as_ref();
+    write_tiled_map(level, path)?;
+
+    return write_tileset_image(path.with_file_name(TILESET_IMAGE));
+}
+
+// writes a .tmj map for a .tmj or .json path and a .tmx map for anything else
+// the map expects TILESET_IMAGE next to it, maps written together can share one
+pub fn write_tiled_map<P: AsRef<Path>>(level: &Level, path: P) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let map = create_map(level);
+
+    let writer = BufWriter::new(File::create(path)?);
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("tmj") | Some("json") => serde_json::to_writer(writer, &to_tmj(&map))?,
+        _ => write_tmx(&map, writer)?,
+    }
+
+    return Ok(());
+}
+
+pub fn write_tileset_image<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    let width = TEXTURE_WIDTH * TILESET_COLORS.len() as u32;
+    let height = TEXTURE_HEIGHT;
+
+    let mut image = vec![0; (width * height * 4) as usize];
+    for (i, pixel) in image.chunks_exact_mut(4).enumerate() {
+        let x = i as u32 % width;
+        pixel.copy_from_slice(&TILESET_COLORS[(x / TEXTURE_WIDTH) as usize].to_be_bytes());
+    }
+
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image)?;
+
+    return Ok(());
+}
+
+fn create_map(level: &Level) -> TiledMap {
+    let ground = level.tiles.iter().map(|tile| if *tile == Tile::Gravel {GRAVEL_GID} else {GRASS_GID}).collect();
+
+    // rasterize the roads and safe zone the same way the pixel renderer does, any touched tile is part of the layer
+    let mut road_canvas = Canvas::create(level.width, level.height, level.safe_zone);
+    for road in level.roads.iter().filter(|road| road.is_drawable()) {
+        road.render(&mut road_canvas);
+    }
+    let roads = road_canvas.pixels.iter().map(|pixel| if *pixel != BACKGROUND {GRAVEL_GID} else {EMPTY_GID}).collect();
+
+    let mut safe_zone_canvas = Canvas::create(level.width, level.height, level.safe_zone);
+    let safe_zone = level.safe_zone;
+    safe_zone_canvas.draw_oval(safe_zone.center[0], safe_zone.center[1], safe_zone.width, safe_zone.height, 0xffffff, 1.0);
+    let safe_zone = safe_zone_canvas.pixels.iter().map(|pixel| if *pixel != BACKGROUND {SAFE_ZONE_GID} else {EMPTY_GID}).collect();
+
+    // world position (0, 0) is the center of the map
+    let offset = [
+        (level.width / 2) as f32 * TEXTURE_WIDTH as f32,
+        (level.height / 2) as f32 * TEXTURE_HEIGHT as f32,
+    ];
+
+    let walls = level.walls.iter().into_iter().map(|(name, wall)| {
+        return create_polyline(name.to_string(), "wall", wall.lines.clone(), None, offset);
+    }).collect();
+
+    let road_lines = level.roads.iter().enumerate().filter(|(_index, road)| road.is_drawable()).map(|(index, road)| {
+        let points = road.points.iter().map(|point| [point.x, point.y]).collect();
+        let widths = road.points.iter().map(|point| point.thickness).collect();
+
+        return create_polyline(format!("road_{}", index), "road", points, Some(widths), offset);
+    }).collect();
+
+    return TiledMap {
+        width: level.width,
+        height: level.height,
+        tile_layers: vec![
+            TileLayer { name: "ground", data: ground },
+            TileLayer { name: "roads", data: roads },
+            TileLayer { name: "safe_zone", data: safe_zone },
+        ],
+        object_groups: vec![
+            ObjectGroup { name: "walls", objects: walls },
+            ObjectGroup { name: "roads", objects: road_lines },
+        ],
+    };
+}
+
+fn create_polyline(name: String, class: &'static str, points: Vec<[f32; 2]>, widths: Option<Vec<f32>>, offset: [f32; 2]) -> Polyline {
+    let first = points.first().copied().unwrap_or([0.0, 0.0]);
+    let position = [first[0] + offset[0], first[1] + offset[1]];
+    let points = points.iter().map(|point| [point[0] - first[0], point[1] - first[1]]).collect();
+
+    return Polyline {
+        name,
+        class,
+        position,
+        points,
+        widths,
+    };
+}
+
+fn format_widths(widths: &[f32]) -> String {
+    return widths.iter().map(|width| width.to_string()).collect::<Vec<String>>().join(",");
+}
+
+fn to_tmj(map: &TiledMap) -> Value {
+    let mut layer_id = 1;
+    let mut object_id = 1;
+
+    let mut layers = Vec::new();
+    for layer in &map.tile_layers {
+        layers.push(json!({
+            "type": "tilelayer",
+            "id": layer_id,
+            "name": layer.name,
+            "x": 0,
+            "y": 0,
+            "width": map.width,
+            "height": map.height,
+            "opacity": 1,
+            "visible": true,
+            "data": layer.data,
+        }));
+        layer_id += 1;
+    }
+
+    for group in &map.object_groups {
+        let mut objects = Vec::new();
+        for polyline in &group.objects {
+            let mut object = json!({
+                "id": object_id,
+                "name": polyline.name,
+                "type": polyline.class,
+                "x": polyline.position[0],
+                "y": polyline.position[1],
+                "width": 0,
+                "height": 0,
+                "rotation": 0,
+                "visible": true,
+                "polyline": polyline.points.iter().map(|point| json!({"x": point[0], "y": point[1]})).collect::<Vec<Value>>(),
+            });
+            if let Some(widths) = &polyline.widths {
+                object["properties"] = json!([{"name": "widths", "type": "string", "value": format_widths(widths)}]);
+            }
+
+            objects.push(object);
+            object_id += 1;
+        }
+
+        layers.push(json!({
+            "type": "objectgroup",
+            "id": layer_id,
+            "name": group.name,
+            "x": 0,
+            "y": 0,
+            "opacity": 1,
+            "visible": true,
+            "draworder": "topdown",
+            "objects": objects,
+        }));
+        layer_id += 1;
+    }
+
+    return json!({
+        "type": "map",
+        "version": "1.10",
+        "tiledversion": "1.10.0",
+        "orientation": "orthogonal",
+        "renderorder": "right-down",
+        "infinite": false,
+        "width": map.width,
+        "height": map.height,
+        "tilewidth": TEXTURE_WIDTH,
+        "tileheight": TEXTURE_HEIGHT,
+        "nextlayerid": layer_id,
+        "nextobjectid": object_id,
+        "tilesets": [{
+            "firstgid": 1,
+            "name": "moonlight",
+            "tilewidth": TEXTURE_WIDTH,
+            "tileheight": TEXTURE_HEIGHT,
+            "tilecount": TILESET_COLORS.len(),
+            "columns": TILESET_COLORS.len(),
+            "margin": 0,
+            "spacing": 0,
+            "image": TILESET_IMAGE,
+            "imagewidth": TEXTURE_WIDTH as usize * TILESET_COLORS.len(),
+            "imageheight": TEXTURE_HEIGHT,
+        }],
+        "layers": layers,
+    });
+}
+
+fn write_tmx<W: Write>(map: &TiledMap, mut writer: W) -> std::io::Result<()> {
+    let layer_count = map.tile_layers.len() + map.object_groups.len();
+    let object_count: usize = map.object_groups.iter().map(|group| group.objects.len()).sum();
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<map version="1.10" tiledversion="1.10.0" orientation="orthogonal" renderorder="right-down" width="{}" height="{}" tilewidth="{}" tileheight="{}" infinite="0" nextlayerid="{}" nextobjectid="{}">"#,
+        map.width, map.height, TEXTURE_WIDTH, TEXTURE_HEIGHT, layer_count + 1, object_count + 1)?;
+    writeln!(
+        writer,
+        r#" <tileset firstgid="1" name="moonlight" tilewidth="{}" tileheight="{}" tilecount="{}" columns="{}">"#,
+        TEXTURE_WIDTH, TEXTURE_HEIGHT, TILESET_COLORS.len(), TILESET_COLORS.len())?;
+    writeln!(
+        writer,
+        r#"  <image source="{}" width="{}" height="{}"/>"#,
+        TILESET_IMAGE, TEXTURE_WIDTH as usize * TILESET_COLORS.len(), TEXTURE_HEIGHT)?;
+    writeln!(writer, r#" </tileset>"#)?;
+
+    let mut layer_id = 1;
+    let mut object_id = 1;
+
+    for layer in &map.tile_layers {
+        writeln!(writer, r#" <layer id="{}" name="{}" width="{}" height="{}">"#, layer_id, layer.name, map.width, map.height)?;
+        writeln!(writer, r#"  <data encoding="csv">"#)?;
+        for (y, row) in layer.data.chunks(map.width).enumerate() {
+            let row = row.iter().map(|gid| gid.to_string()).collect::<Vec<String>>().join(",");
+            if y + 1 < map.height {
+                writeln!(writer, "{},", row)?;
+            } else {
+                writeln!(writer, "{}", row)?;
+            }
+        }
+        writeln!(writer, r#"  </data>"#)?;
+        writeln!(writer, r#" </layer>"#)?;
+        layer_id += 1;
+    }
+
+    for group in &map.object_groups {
+        writeln!(writer, r#" <objectgroup id="{}" name="{}">"#, layer_id, group.name)?;
+        for polyline in &group.objects {
+            writeln!(
+                writer,
+                r#"  <object id="{}" name="{}" type="{}" x="{}" y="{}">"#,
+                object_id, polyline.name, polyline.class, polyline.position[0], polyline.position[1])?;
+            if let Some(widths) = &polyline.widths {
+                writeln!(writer, r#"   <properties>"#)?;
+                writeln!(writer, r#"    <property name="widths" value="{}"/>"#, format_widths(widths))?;
+                writeln!(writer, r#"   </properties>"#)?;
+            }
+
+            let points = polyline.points.iter().map(|point| format!("{},{}", point[0], point[1])).collect::<Vec<String>>().join(" ");
+            writeln!(writer, r#"   <polyline points="{}"/>"#, points)?;
+            writeln!(writer, r#"  </object>"#)?;
+            object_id += 1;
+        }
+        writeln!(writer, r#" </objectgroup>"#)?;
+        layer_id += 1;
+    }
+
+    writeln!(writer, r#"</map>"#)?;
+
+    return writer.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tmj_layers() {
+        let level = crate::generate(0);
+        let map = to_tmj(&create_map(&level));
+
+        let layers = map["layers"].as_array().unwrap();
+        assert_eq!(layers.len(), 5);
+        for layer in &layers[0..3] {
+            assert_eq!(layer["type"], "tilelayer");
+            assert_eq!(layer["data"].as_array().unwrap().len(), level.width * level.height);
+        }
+
+        assert_eq!(layers[3]["name"], "walls");
+        assert_eq!(layers[3]["objects"].as_array().unwrap().len(), level.walls.iter().len());
+        assert_eq!(layers[4]["name"], "roads");
+        assert_eq!(map["nextobjectid"], level.walls.iter().len() + layers[4]["objects"].as_array().unwrap().len() + 1);
+    }
+
+    #[test]
+    fn tmx_rows() {
+        let level = crate::generate(0);
+        let mut tmx = Vec::new();
+        write_tmx(&create_map(&level), &mut tmx).unwrap();
+        let tmx = String::from_utf8(tmx).unwrap();
+
+        assert_eq!(tmx.matches("<layer ").count(), 3);
+        assert_eq!(tmx.matches("<objectgroup ").count(), 2);
+
+        // every csv row of every tile layer is width gids long
+        let rows: Vec<&str> = tmx.lines().filter(|line| line.starts_with(|c: char| c.is_ascii_digit())).collect();
+        assert_eq!(rows.len(), level.height * 3);
+        for row in rows {
+            assert_eq!(row.trim_end_matches(',').split(',').count(), level.width);
+        }
+    }
+
+    #[test]
+    fn tileset_is_only_written_with_the_map_when_asked_for() {
+        let level = crate::generate(0);
+        let directory = std::env::temp_dir().join(format!("moonlight_tiled_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        level.write_tiled_map(directory.join("map.tmx")).unwrap();
+        assert!(directory.join("map.tmx").exists());
+        assert!(!directory.join(TILESET_IMAGE).exists());
+
+        level.write_tiled(directory.join("map.tmj")).unwrap();
+        assert!(directory.join(TILESET_IMAGE).exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn polylines_are_relative_to_their_first_point() {
+        let polyline = create_polyline("wall".to_string(), "wall", vec![[10.0, 20.0], [30.0, 50.0]], None, [100.0, 200.0]);
+
+        assert_eq!(polyline.position, [110.0, 220.0]);
+        assert_eq!(polyline.points, vec![[0.0, 0.0], [20.0, 30.0]]);
+    }
+}
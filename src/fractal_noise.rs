@@ -0,0 +1,138 @@
+use crate::perlin::Perlin;
+use serde::{Serialize, Deserialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseBasis {
+    Perlin,
+    Simplex,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FractalMode {
+    // plain sum of octaves, in [-1, 1]
+    Fbm,
+    // sharp ridges where the noise crosses zero, each octave weighted by the one before it, in [-1, 1]
+    Ridged,
+    // rounded bumps from the absolute value of each octave, in [-1, 1]
+    Billow,
+    // the absolute value of each octave without recentering, in [0, 1]
+    Turbulence,
+}
+
+// layers octaves of a basis noise, each one lacunarity times the frequency and gain times the amplitude of the last
+// a single octave of fbm is exactly the basis noise
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FractalNoise {
+    pub basis: NoiseBasis,
+    pub mode: FractalMode,
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl FractalNoise {
+    pub fn sample(&self, noise: &Perlin, x: f32, y: f32) -> f32 {
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        let mut amplitude_total = 0.0;
+        let mut total = 0.0;
+        // ridged multifractal weight, detail only shows up on top of existing ridges
+        let mut weight = 1.0;
+
+        for _octave in 0..self.octaves.max(1) {
+            let value = match self.basis {
+                NoiseBasis::Perlin => noise.perlin2(x * frequency, y * frequency),
+                NoiseBasis::Simplex => noise.simplex2(x * frequency, y * frequency),
+            };
+
+            let value = match self.mode {
+                FractalMode::Fbm => value,
+                FractalMode::Ridged => {
+                    let ridge = (1.0 - value.abs()).powi(2) * weight;
+                    weight = (ridge * 2.0).clamp(0.0, 1.0);
+                    ridge * 2.0 - 1.0
+                },
+                FractalMode::Billow => value.abs() * 2.0 - 1.0,
+                FractalMode::Turbulence => value.abs(),
+            };
+
+            total += value * amplitude;
+            amplitude_total += amplitude;
+
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+
+        return total / amplitude_total;
+    }
+}
+
+impl Default for FractalNoise {
+    fn default() -> Self {
+        return FractalNoise {
+            basis: NoiseBasis::Perlin,
+            mode: FractalMode::Fbm,
+            octaves: 1,
+            lacunarity: 2.0,
+            gain: 0.5,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_octave_of_fbm_is_the_basis() {
+        let noise = Perlin::create(1);
+        let simplex = FractalNoise {
+            basis: NoiseBasis::Simplex,
+            ..FractalNoise::default()
+        };
+
+        assert_eq!(FractalNoise::default().sample(&noise, 0.4, 1.7), noise.perlin2(0.4, 1.7));
+        assert_eq!(simplex.sample(&noise, 0.4, 1.7), noise.simplex2(0.4, 1.7));
+    }
+
+    #[test]
+    fn modes_stay_in_range() {
+        let noise = Perlin::create(2);
+        for mode in [FractalMode::Fbm, FractalMode::Ridged, FractalMode::Billow, FractalMode::Turbulence] {
+            let fractal = FractalNoise {
+                mode,
+                octaves: 5,
+                ..FractalNoise::default()
+            };
+            let min = if mode == FractalMode::Turbulence {0.0} else {-1.0};
+
+            for index in 0..500 {
+                let value = fractal.sample(&noise, index as f32 * 0.113, index as f32 * 0.057);
+                assert!(value >= min && value <= 1.0, "{:?} gave {}", mode, value);
+            }
+        }
+    }
+
+    #[test]
+    fn octaves_add_detail() {
+        let noise = Perlin::create(3);
+        let fractal = FractalNoise {
+            octaves: 4,
+            ..FractalNoise::default()
+        };
+
+        // the extra octaves are normalized back down, so with them the value differs from the basis
+        let base = FractalNoise::default().sample(&noise, 0.37, 0.81);
+        assert_ne!(fractal.sample(&noise, 0.37, 0.81), base);
+
+        // zero octaves still samples one
+        let none = FractalNoise {
+            octaves: 0,
+            ..FractalNoise::default()
+        };
+        assert_eq!(none.sample(&noise, 0.37, 0.81), base);
+    }
+}
@@ -1,18 +1,20 @@
 use crate::{SCREEN_WIDTH, SCREEN_HEIGHT};
-use line_drawing::Bresenham;
 use crate::perlin::Perlin;
+use crate::domain_warp::DomainWarp;
 use crate::random::Random;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use crate::math_helper::MathHelper;
 use crate::helpers::wall_section::WallSection;
 use std::f32::consts::PI;
 use std::hash::{Hash, Hasher};
 use std::cmp::Ordering;
-use crate::helpers::road_segment::RoadSegment;
-
-const NOISE_DETAIL: f32 = 0.0005 / 0.75;
-const APPROX_WIDTH: f32 = 10752.0;
-const APPROX_HEIGHT: f32 = 10752.0;
+use std::sync::OnceLock;
+use crate::helpers::road_segment::{RoadSegment, ROUTE_STEP_LENGTH};
+use crate::helpers::road_cleanup::RoadCleanup;
+use crate::config::GeneratorConfig;
+use crate::level::{Level, LevelWalls, LevelZones, SafeZone, Tile, WallPath};
+use crate::level_index::LevelIndex;
+use crate::helpers::zone_polygon::ZonePolygon;
 
 const TILE_WIDTH: u32 = 4;
 const TILE_HEIGHT: u32 = 3;
@@ -26,17 +28,60 @@ const SCREEN_SCALE: u32 = 2;
 const LEVEL_WIDTH: usize = SCREEN_WIDTH as usize / TILE_WIDTH as usize * SCREEN_SCALE as usize;
 const LEVEL_HEIGHT: usize = SCREEN_HEIGHT as usize / TILE_HEIGHT as usize * SCREEN_SCALE as usize;
 
-const NEW_SAFE_ZONE_SCALE_MUL: f32 = 2.0;
-
-pub const SAFE_ZONE_WIDTH: f32 = 3072.0 * NEW_SAFE_ZONE_SCALE_MUL;
-pub const SAFE_ZONE_HEIGHT: f32 = 2304.0 * NEW_SAFE_ZONE_SCALE_MUL;
-
 pub struct Generator {
-    pixels: Vec<u32>,
-    grass: Vec<bool>,
+    tiles: Vec<Tile>,
     random: Random,
-    noise: Perlin,
-    seed: i64,
+    noise_layers: Vec<Perlin>,
+    // the x and y fields each layer's domain warp reads its offsets from
+    warp_layers: Vec<[Perlin; 2]>,
+    seed: u64,
+    config: GeneratorConfig,
+    // the walls and the paths through them, set once the walls are done so the roads can avoid them
+    wall_index: Option<LevelIndex>,
+    paths: Vec<WallPath>,
+}
+
+// the features that sample noise, each has its own independently seeded field
+// the secondary layers are a second field for features that multiply or combine two samples
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum NoiseLayer {
+    RoadTiles,
+    RoadTilesSecondary,
+    RoadDrift,
+    RoadThickness,
+    RoadThicknessSecondary,
+    WallDrift,
+    WallWobble,
+    WallThickness,
+    WallThicknessSecondary,
+}
+
+impl NoiseLayer {
+    pub const ALL: [NoiseLayer; 9] = [
+        NoiseLayer::RoadTiles,
+        NoiseLayer::RoadTilesSecondary,
+        NoiseLayer::RoadDrift,
+        NoiseLayer::RoadThickness,
+        NoiseLayer::RoadThicknessSecondary,
+        NoiseLayer::WallDrift,
+        NoiseLayer::WallWobble,
+        NoiseLayer::WallThickness,
+        NoiseLayer::WallThicknessSecondary,
+    ];
+
+    pub fn get_label(&self) -> &'static str {
+        return match self {
+            NoiseLayer::RoadTiles => "road tiles noise",
+            NoiseLayer::RoadTilesSecondary => "road tiles secondary noise",
+            NoiseLayer::RoadDrift => "road drift noise",
+            NoiseLayer::RoadThickness => "road thickness noise",
+            NoiseLayer::RoadThicknessSecondary => "road thickness secondary noise",
+            NoiseLayer::WallDrift => "wall drift noise",
+            NoiseLayer::WallWobble => "wall wobble noise",
+            NoiseLayer::WallThickness => "wall thickness noise",
+            NoiseLayer::WallThicknessSecondary => "wall thickness secondary noise",
+        };
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -69,35 +114,42 @@ impl Hash for Angle {
 }
 
 impl Generator {
-    pub fn render(&mut self, frame: &mut [u8]) {
-        for value in &mut self.pixels {
-            *value = 0x000000ff;
-        }
-
-        self.generate_level();
-
-        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
-
-            let x = (i % SCREEN_WIDTH as usize) as f64 / SCREEN_WIDTH as f64;
-            let y = (i / SCREEN_WIDTH as usize) as f64 / SCREEN_HEIGHT as f64;
-            let x = (x * LEVEL_WIDTH as f64) as usize;
-            let y = (y * LEVEL_HEIGHT as f64) as usize;
+    pub fn with_seed(seed: u64) -> Self {
+        return Generator::with_config(seed, GeneratorConfig::default());
+    }
 
-            let index = y * LEVEL_WIDTH + x;
-            let color = self.pixels[index];
+    pub fn with_config(seed: u64, config: GeneratorConfig) -> Self {
+        return Generator {
+            tiles: vec![Tile::Grass; LEVEL_WIDTH * LEVEL_HEIGHT],
+            random: Random::create(seed),
+            noise_layers: NoiseLayer::ALL.iter().map(|_layer| Perlin::default()).collect(),
+            warp_layers: NoiseLayer::ALL.iter().map(|_layer| [Perlin::default(), Perlin::default()]).collect(),
+            seed,
+            config,
+            wall_index: None,
+            paths: Vec::new(),
+        };
+    }
 
-            pixel[0] = ((color & 0xff000000) >> 24) as u8;
-            pixel[1] = ((color & 0x00ff0000) >> 16) as u8;
-            pixel[2] = ((color & 0x0000ff00) >> 8) as u8;
-            pixel[3] = (color & 0x000000ff) as u8;
+    pub fn generate(&mut self) -> Level {
+        for value in &mut self.tiles {
+            *value = Tile::Grass;
         }
+
+        return self.generate_level();
     }
 
-    fn generate_level(&mut self) {
-        self.noise.seed(self.next());
+    fn generate_level(&mut self) -> Level {
+        // every layer gets its own noise field instead of sampling one field at different offsets
+        for layer in NoiseLayer::ALL {
+            let mut random = Random::create(self.seed).fork(layer.get_label());
+            self.noise_layers[layer as usize].seed(random.next_u64());
+            self.warp_layers[layer as usize][0].seed(random.fork("warp x").next_u64());
+            self.warp_layers[layer as usize][1].seed(random.fork("warp y").next_u64());
+        }
 
         // road tiles
-        let mut bool_tiles = self.create_road_bool_tiles();
+        let bool_tiles = self.create_road_bool_tiles();
         for x in 0..LEVEL_WIDTH as i32 {
             for y in 0..LEVEL_HEIGHT as i32 {
                 let index = y * LEVEL_WIDTH as i32 + x;
@@ -108,32 +160,54 @@ impl Generator {
                 }
 
                 if bool_tiles[&ix][&iy] {
-                    self.grass[index as usize] = false;
+                    self.tiles[index as usize] = Tile::Gravel;
                 }
             }
         }
-        for (x, map) in bool_tiles.into_iter() {
-            for (y, gravel) in map.into_iter() {
-                self.draw_tile(x, y, if gravel {0xffffff} else {0x43711d}, 1.0);
-            }
-        }
-
-        // calculate the road segments at the exact edge of the safe zone
-        self.generate_roads(0.0, 0.0, SAFE_ZONE_WIDTH, SAFE_ZONE_HEIGHT, 0xff0000, 0.8);
 
         // safe zone
-        self.draw_oval(0.0, 0.0, SAFE_ZONE_WIDTH, SAFE_ZONE_HEIGHT, 0x39a8e7, 0.5);
+        let safe_zone_width = self.config.get_safe_zone_width();
+        let safe_zone_height = self.config.get_safe_zone_height();
+        let safe_zone = SafeZone {
+            center: [0.0, 0.0],
+            width: safe_zone_width,
+            height: safe_zone_height,
+        };
 
-        // walls
-        self.generate_walls();
+        // walls, before the roads so the roads can run into them
+        let (walls, zones, paths) = self.generate_walls(&safe_zone);
+        self.wall_index = Some(LevelIndex::create(&walls, &zones));
+        self.paths = paths;
+
+        // calculate the road segments at the exact edge of the safe zone
+        let mut roads = self.generate_roads(0.0, 0.0, safe_zone_width, safe_zone_height);
+        self.generate_path_roads(&mut roads, &safe_zone);
+        let roads = RoadCleanup::from_config(&self.config).apply(self, roads, &safe_zone);
+
+        return Level {
+            seed: self.seed,
+            width: LEVEL_WIDTH,
+            height: LEVEL_HEIGHT,
+            tiles: self.tiles.clone(),
+            safe_zone,
+            walls,
+            zones,
+            roads,
+            // the index was already built for the roads
+            index: self.wall_index.take().map_or_else(OnceLock::new, OnceLock::from),
+            road_network: OnceLock::new(),
+        };
     }
 
-    fn generate_walls(&mut self) {
+    fn generate_walls(&mut self, safe_zone: &SafeZone) -> (LevelWalls, LevelZones, Vec<WallPath>) {
+        let config = self.config;
+
         // generate the angle for the big area walls
-        let t1_t3_angle = self.next() * PI * 2.0;
+        let (t1_t3_angle, t3_t2_angle_offset) = self.with_stream("wall angles", |generator| (generator.next(), generator.next()));
+        let t1_t3_angle = t1_t3_angle * PI * 2.0;
         let t1_t3_angle_original = t1_t3_angle;
         let t3_t2_angle = t1_t3_angle + PI;
-        let t3_t2_angle = t3_t2_angle + self.next() * PI * 0.1 - PI * 0.05;
+        let t3_t2_angle = t3_t2_angle + t3_t2_angle_offset * PI * 0.1 - PI * 0.05;
 
         // generate the angle for the small area divider wall
         let t2_t1_angle = MathHelper::radians_between_angles(t3_t2_angle, t1_t3_angle);
@@ -142,19 +216,16 @@ impl Generator {
 
         // generate out the divider walls with random curves and metadata for the thickness along the path
         let mut t1_t3_wall = WallSection::default();
-        let desired_wall_length = 30000.0 + 12000.0 * self.next();
-        t1_t3_wall.fill_wall(self, 45000.0, t1_t3_angle, t1_t3_angle, 0.08, None);
-        let [mut t1_t3_wall_1, mut t1_t3_wall_2] = t1_t3_wall.thicken(self, 200.0, 1200.0);
+        self.with_stream("t1_t3 wall fill", |generator| t1_t3_wall.fill_wall(generator, config.wall_length, t1_t3_angle, t1_t3_angle, 0.08, None));
+        let [mut t1_t3_wall_1, mut t1_t3_wall_2] = self.with_stream("t1_t3 wall thicken", |generator| t1_t3_wall.thicken(generator, config.wall_start_thickness, config.wall_end_thickness));
 
         let mut t3_t2_wall = WallSection::default();
-        let desired_wall_length = 30000.0 + 12000.0 * self.next();
-        t3_t2_wall.fill_wall(self, 45000.0, t3_t2_angle, t3_t2_angle, 0.08, None);
-        let [mut t3_t2_wall_1, mut t3_t2_wall_2] = t3_t2_wall.thicken(self, 200.0, 1200.0);
+        self.with_stream("t3_t2 wall fill", |generator| t3_t2_wall.fill_wall(generator, config.wall_length, t3_t2_angle, t3_t2_angle, 0.08, None));
+        let [mut t3_t2_wall_1, mut t3_t2_wall_2] = self.with_stream("t3_t2 wall thicken", |generator| t3_t2_wall.thicken(generator, config.wall_start_thickness, config.wall_end_thickness));
 
         let mut t2_t1_wall = WallSection::default();
-        let desired_wall_length = 30000.0 + 12000.0 * self.next();
-        t2_t1_wall.fill_wall(self, 45000.0, t2_t1_angle, t2_t1_angle, 0.08, Some(&t1_t3_wall_1));
-        let [mut t2_t1_wall_1, mut t2_t1_wall_2] = t2_t1_wall.thicken(self, 200.0, 1200.0);
+        self.with_stream("t2_t1 wall fill", |generator| t2_t1_wall.fill_wall(generator, config.wall_length, t2_t1_angle, t2_t1_angle, 0.08, Some(&t1_t3_wall_1)));
+        let [mut t2_t1_wall_1, mut t2_t1_wall_2] = self.with_stream("t2_t1 wall thicken", |generator| t2_t1_wall.thicken(generator, config.wall_start_thickness, config.wall_end_thickness));
 
         // TODO I should do this some better way than literally halving it
         let point1 = t2_t1_wall_2.get_point_at_length(12000.0);
@@ -194,22 +265,22 @@ impl Generator {
         t1_wall_upper.noiseify(self, 6000.0, 12.0, [0.0, 0.0], PI / 2.0);
 
         // t1 inner closing wall
-        let mut t1_wall_closing = self.close_walls(&t1_wall_lower, &t1_wall_upper);
+        let t1_wall_closing = self.close_walls(&t1_wall_lower, &t1_wall_upper);
 
         // t1 t3 closing wall
-        let mut t1_t3_wall_closing = self.close_walls(&t1_t3_wall_1, &t1_t3_wall_2);
+        let t1_t3_wall_closing = self.close_walls(&t1_t3_wall_1, &t1_t3_wall_2);
 
         // t3 t2 closing wall
-        let mut t3_t2_wall_closing = self.close_walls(&t3_t2_wall_1, &t3_t2_wall_2);
+        let t3_t2_wall_closing = self.close_walls(&t3_t2_wall_1, &t3_t2_wall_2);
 
-        let mut t2_t1_wall_closing = self.close_walls(&t2_t1_wall_1, &t2_t1_wall_2);
+        let t2_t1_wall_closing = self.close_walls(&t2_t1_wall_1, &t2_t1_wall_2);
 
         // back walls
-        let tangent_strength = 24000.0;
+        let tangent_strength = config.t1_back_wall_tangent_strength;
 
         // t1 upper bounding wall
         let t1_upper_point = t1_wall_upper.get_last_point();
-        let mut length = (t1_upper_point[0] * t1_upper_point[0] + t1_upper_point[1] * t1_upper_point[1]).sqrt().min(32000.0);
+        let length = (t1_upper_point[0] * t1_upper_point[0] + t1_upper_point[1] * t1_upper_point[1]).sqrt().min(32000.0);
         t1_wall_upper.delete_after_length(length);
         let t1_upper_point = t1_wall_upper.get_last_point();
         let t1_upper_tangent = [t1_upper_point[1].atan2(t1_upper_point[0]).cos() * tangent_strength, t1_upper_point[1].atan2(t1_upper_point[0]).sin() * tangent_strength];
@@ -253,10 +324,10 @@ impl Generator {
         center_point[1] -= center_point[1] / center_dist * 10000.0;
         t1_lower_back_wall.noiseify(self, 400.0, 1.0, center_point, 0.0);
 
-        let tangent_strength = 54000.0;
+        let tangent_strength = config.t2_back_wall_tangent_strength;
 
         // t2 bounding wall
-        let mut length = 18000.0;
+        let length = 18000.0;
         t3_t2_wall_2.delete_after_length(length);
         let t3_t2_point = t3_t2_wall_2.get_last_point();
         let t3_t2_tangent = [t3_t2_point[1].atan2(t3_t2_point[0]).cos() * tangent_strength, t3_t2_point[1].atan2(t3_t2_point[0]).sin() * tangent_strength];
@@ -282,13 +353,11 @@ impl Generator {
         t1_t3_wall_2.delete_after_length(length);
         let t1_t3_point = t1_t3_wall_2.get_last_point();
         let t1_t3_dist = (t1_t3_point[0] * t1_t3_point[0] + t1_t3_point[1] * t1_t3_point[1]).sqrt();
-        let t1_t3_angle = t1_t3_point[1].atan2(t1_t3_point[0]);
 
         let length = 18000.0;
         t3_t2_wall_1.delete_after_length(length);
         let t3_t2_point = t3_t2_wall_1.get_last_point();
         let t3_t2_dist = (t3_t2_point[0] * t3_t2_point[0] + t3_t2_point[1] * t3_t2_point[1]).sqrt();
-        let t3_t2_angle = t3_t2_point[1].atan2(t3_t2_point[0]);
 
         let t1_t3_angle = t1_t3_point[1].atan2(t1_t3_point[0]);
         let t3_t2_angle = t3_t2_point[1].atan2(t3_t2_point[0]);
@@ -298,7 +367,6 @@ impl Generator {
         }
 
         let start_angle_offset = ((angle_diff - (144.0 / 180.0 * PI)).max(15.0 / 180.0 * PI) + (24.0 / 180.0 * PI)).min(50.0 / 180.0 * PI);
-        let start_angle_offset = start_angle_offset;
         let start_angle = t1_t3_angle - start_angle_offset;
         // I remember basic trig
         // adding this back makes it so it doesn't extend as far when bend obtusely or whatever
@@ -315,7 +383,7 @@ impl Generator {
         let mut t3_back_wall = WallSection::default();
         t3_back_wall.connect_points_linear(self, start_point, end_point);
 
-        let tangent_strength = 8000.0;
+        let tangent_strength = config.t3_side_wall_tangent_strength;
 
         let mut t1_t3_side_wall = WallSection::default();
         t1_t3_side_wall.connect_points(
@@ -342,15 +410,26 @@ impl Generator {
 
         // self.draw_line(start_point[0], start_point[1], end_point[0], end_point[1], 0xff0000, 1.0);
 
+        // the tier zones are built from the divider walls as they are before the paths are cut through them
+        // the t1 t3 divider ends up in the outer wall so it's kept for them here
+        let zone_t1_t3_wall_1 = t1_t3_wall_1.clone();
+        let zone_t1_t3_wall_2 = t1_t3_wall_2.clone();
+
+        // the openings cut through the divider walls below
+        let mut paths = Vec::new();
+
         // t2 t1 connector
         let t2_t1_wall_1_length = t2_t1_wall_1.get_length();
         let t2_t1_wall_2_length = t2_t1_wall_2.get_length();
-        let t2_t1_path_length = t2_t1_wall_1_length.min(t2_t1_wall_2_length) * (0.6 + self.next() * 0.3);
+        let (t2_t1_path_length, t2_t1_path_thickness) = self.with_stream("t2_t1 path", |generator| (generator.next(), generator.next()));
+        let t2_t1_path_length = t2_t1_wall_1_length.min(t2_t1_wall_2_length) * (0.6 + t2_t1_path_length * 0.3);
+        let t2_t1_path_thickness = config.path_min_thickness + config.path_thickness_range * t2_t1_path_thickness;
+        let [mut t2_t1_wall_1_split_1, t2_t1_wall_1_split_2] = t2_t1_wall_1.split_for_path(t2_t1_path_length, t2_t1_path_thickness);
 
-        let t2_t1_path_thickness = 900.0 + 1400.0 * self.next();
-        let [mut t2_t1_wall_1_split_1, mut t2_t1_wall_1_split_2] = t2_t1_wall_1.split_for_path(t2_t1_path_length, t2_t1_path_thickness);
-
-        let [mut t2_t1_wall_2_split_1, mut t2_t1_wall_2_split_2] = t2_t1_wall_2.split_for_path(t2_t1_path_length, t2_t1_path_thickness);
+        let [t2_t1_wall_2_split_1, t2_t1_wall_2_split_2] = t2_t1_wall_2.split_for_path(t2_t1_path_length, t2_t1_path_thickness);
+        paths.push(WallPath::between(
+            [t2_t1_wall_1_split_1.get_last_point(), t2_t1_wall_1_split_2.get_first_point()],
+            [t2_t1_wall_2_split_1.get_last_point(), t2_t1_wall_2_split_2.get_first_point()]));
 
         let mut t2_t1_path_wall_1 = WallSection::default();
         let point1 = t2_t1_wall_1_split_1.get_last_point();
@@ -387,12 +466,15 @@ impl Generator {
         // t3 t2 connector
         let t3_t2_wall_1_length = t3_t2_wall_1.get_length();
         let t3_t2_wall_2_length = t3_t2_wall_2.get_length();
-        let t3_t2_path_length = t3_t2_wall_1_length.min(t3_t2_wall_2_length) * (0.6 + self.next() * 0.3);
-
-        let t3_t2_path_thickness = 900.0 + 1400.0 * self.next();
-        let [mut t3_t2_wall_1_split_1, mut t3_t2_wall_1_split_2] = t3_t2_wall_1.split_for_path(t3_t2_path_length, t3_t2_path_thickness);
+        let (t3_t2_path_length, t3_t2_path_thickness) = self.with_stream("t3_t2 path", |generator| (generator.next(), generator.next()));
+        let t3_t2_path_length = t3_t2_wall_1_length.min(t3_t2_wall_2_length) * (0.6 + t3_t2_path_length * 0.3);
+        let t3_t2_path_thickness = config.path_min_thickness + config.path_thickness_range * t3_t2_path_thickness;
+        let [mut t3_t2_wall_1_split_1, t3_t2_wall_1_split_2] = t3_t2_wall_1.split_for_path(t3_t2_path_length, t3_t2_path_thickness);
 
-        let [mut t3_t2_wall_2_split_1, mut t3_t2_wall_2_split_2] = t3_t2_wall_2.split_for_path(t3_t2_path_length, t3_t2_path_thickness);
+        let [t3_t2_wall_2_split_1, t3_t2_wall_2_split_2] = t3_t2_wall_2.split_for_path(t3_t2_path_length, t3_t2_path_thickness);
+        paths.push(WallPath::between(
+            [t3_t2_wall_1_split_1.get_last_point(), t3_t2_wall_1_split_2.get_first_point()],
+            [t3_t2_wall_2_split_1.get_last_point(), t3_t2_wall_2_split_2.get_first_point()]));
 
         let mut t3_t2_path_wall_1 = WallSection::default();
         let point1 = t3_t2_wall_1_split_1.get_last_point();
@@ -429,12 +511,15 @@ impl Generator {
         // t1 first connector
         let t1_wall_lower_length = t1_wall_lower.get_length();
         let t1_wall_upper_length = t1_wall_upper.get_length();
-        let t1_path_length = t1_wall_lower_length.min(t1_wall_upper_length) * (0.25 + self.next() * 0.25);
-
-        let t1_path_thickness = 900.0 + 1400.0 * self.next();
+        let (t1_path_length, t1_path_thickness) = self.with_stream("t1 first path", |generator| (generator.next(), generator.next()));
+        let t1_path_length = t1_wall_lower_length.min(t1_wall_upper_length) * (0.25 + t1_path_length * 0.25);
+        let t1_path_thickness = config.path_min_thickness + config.path_thickness_range * t1_path_thickness;
         let [mut t1_wall_lower_split_1, mut t1_wall_lower_split_2] = t1_wall_lower.split_for_path(t1_path_length, t1_path_thickness);
 
-        let [mut t1_wall_upper_split_1, mut t1_wall_upper_split_2] = t1_wall_upper.split_for_path(t1_path_length, t1_path_thickness);
+        let [t1_wall_upper_split_1, mut t1_wall_upper_split_2] = t1_wall_upper.split_for_path(t1_path_length, t1_path_thickness);
+        paths.push(WallPath::between(
+            [t1_wall_lower_split_1.get_last_point(), t1_wall_lower_split_2.get_first_point()],
+            [t1_wall_upper_split_1.get_last_point(), t1_wall_upper_split_2.get_first_point()]));
 
         let mut t1_path_wall_1 = WallSection::default();
         let point1 = t1_wall_lower_split_1.get_last_point();
@@ -471,12 +556,15 @@ impl Generator {
         // t1 second connector
         let t1_wall_lower_length = t1_wall_lower_split_2.get_length();
         let t1_wall_upper_length = t1_wall_upper_split_2.get_length();
-        let t1_path_length = t1_wall_lower_length.min(t1_wall_upper_length) * (0.50 + self.next() * 0.40);
-
-        let t1_path_thickness = 900.0 + 1400.0 * self.next();
-        let [mut t1_wall_lower_split_2, mut t1_wall_lower_split_3] = t1_wall_lower_split_2.split_for_path(t1_path_length, t1_path_thickness);
+        let (t1_path_length, t1_path_thickness) = self.with_stream("t1 second path", |generator| (generator.next(), generator.next()));
+        let t1_path_length = t1_wall_lower_length.min(t1_wall_upper_length) * (0.50 + t1_path_length * 0.40);
+        let t1_path_thickness = config.path_min_thickness + config.path_thickness_range * t1_path_thickness;
+        let [mut t1_wall_lower_split_2, t1_wall_lower_split_3] = t1_wall_lower_split_2.split_for_path(t1_path_length, t1_path_thickness);
 
-        let [mut t1_wall_upper_split_2, mut t1_wall_upper_split_3] = t1_wall_upper_split_2.split_for_path(t1_path_length, t1_path_thickness);
+        let [t1_wall_upper_split_2, t1_wall_upper_split_3] = t1_wall_upper_split_2.split_for_path(t1_path_length, t1_path_thickness);
+        paths.push(WallPath::between(
+            [t1_wall_lower_split_2.get_last_point(), t1_wall_lower_split_3.get_first_point()],
+            [t1_wall_upper_split_2.get_last_point(), t1_wall_upper_split_3.get_first_point()]));
 
         let mut t1_path_wall_3 = WallSection::default();
         let point1 = t1_wall_lower_split_2.get_last_point();
@@ -510,6 +598,11 @@ impl Generator {
 
         let mut t1_inner_2 = t1_wall_lower_split_2;
 
+        let level_t1_upper_back_wall = t1_upper_back_wall.clone();
+        let level_t1_lower_back_wall = t1_lower_back_wall.clone();
+        let level_t2_back_wall = t2_back_wall.clone();
+        let level_t3_back_wall = t3_back_wall.clone();
+
         t1_t3_wall_2.join_wall(t3_back_wall);
         t1_t3_wall_2.join_wall(t3_t2_wall_1_split_2);
         t1_t3_wall_2.join_wall(t3_t2_path_wall_2);
@@ -528,67 +621,34 @@ impl Generator {
 
         let mut outer_wall = t1_t3_wall_2;
 
-        t1_inner_1.round_to_angle(PI / 8.0);
-        t1_inner_1.remove_loops();
-        t1_inner_2.round_to_angle(PI / 8.0);
-        t1_inner_2.remove_loops();
-        t2_t1_inner.round_to_angle(PI / 8.0);
-        t2_t1_inner.remove_loops();
-        t3_t2_inner.round_to_angle(PI / 8.0);
-        t3_t2_inner.remove_loops();
-        outer_wall.round_to_angle(PI / 8.0);
-        // outer_wall.remove_loops();
-
-        t1_inner_1.render(self, 0x000000);
-        t1_inner_2.render(self, 0x000000);
-
-        t2_t1_inner.render(self, 0x000000);
-        t3_t2_inner.render(self, 0x000000);
-
-        outer_wall.render(self, 0x000000);
-
-        // t1_t3_wall.render(self, 0x880044);
-        // t1_t3_wall_1.render(self, 0x000044);
-        // t1_t3_wall_2.render(self, 0x000044);
-        //
-        // // t3_t2_wall.render(self, 0x880044);
-        // // t3_t2_wall_1.render(self, 0x000044);
-        // // t3_t2_wall_2.render(self, 0x000044);
-        // t3_t2_wall_1_split_2.render(self, 0x440088);
-        // t3_t2_wall_2_split_2.render(self, 0x440088);
-        // t3_t2_path_wall_2.render(self, 0x880044);
-        //
-        // // t2_t1_wall.render(self, 0x880044);
-        // // t2_t1_wall_1_split_1.render(self, 0x000000);
-        // t2_t1_wall_1_split_2.render(self, 0x000000);
-        // // t2_t1_wall_1.render(self, 0x000044);
-        // // t2_t1_wall_2_split_1.render(self, 0x000000);
-        // t2_t1_wall_2_split_2.render(self, 0x000000);
-        // // t2_t1_wall_2.render(self, 0x000044);
-        //
-        // // t2_t1_path_wall_1.render(self, 0x440088);
-        // t2_t1_path_wall_2.render(self, 0x440088);
-        //
-        // // t1_wall_inner.render(self, 0x880044);
-        // // t1_wall_lower.render(self, 0x000044);
-        // // t1_wall_upper.render(self, 0x000044);
-        // // t1_wall_lower_split_2.render(self, 0x000044);
-        // // t1_wall_upper_split_2.render(self, 0x000044);
-        // // t1_path_wall_2.render(self, 0x880044);
-        // t1_wall_lower_split_3.render(self, 0x000044);
-        // t1_wall_upper_split_3.render(self, 0x000044);
-        // t1_path_wall_4.render(self, 0x000044);
-        //
-        // t1_upper_back_wall.render(self, 0x440088);
-        // t1_lower_back_wall.render(self, 0x440088);
-        //
-        // t2_back_wall.render(self, 0x440088);
-        // t3_back_wall.render(self, 0x440088);
-        //
-        // // t1_wall_closing.render(self, 0x440088);
-        // t1_t3_wall_closing.render(self, 0x440088);
-        // // t3_t2_wall_closing.render(self, 0x440088);
-        // // t2_t1_wall_closing.render(self, 0x440088);
+        let mut corners = Vec::new();
+        for wall in [&mut t1_inner_1, &mut t1_inner_2, &mut t2_t1_inner, &mut t3_t2_inner, &mut outer_wall] {
+            corners.extend(wall.get_corners(PI / 8.0));
+            wall.round_to_angle(PI / 8.0);
+            wall.remove_loops();
+        }
+
+        // rounded the same way as the walls so they line up with them
+        let zones = LevelZones {
+            safe_zone: ZonePolygon::from_ellipse(safe_zone.center, safe_zone.width, safe_zone.height),
+            t1: ZonePolygon::from_walls(&[(&t2_t1_wall_2, false), (&level_t1_lower_back_wall, false), (&level_t1_upper_back_wall, false), (&zone_t1_t3_wall_1, true)], &corners, safe_zone),
+            t2: ZonePolygon::from_walls(&[(&t3_t2_wall_2, false), (&level_t2_back_wall, false), (&t2_t1_wall_1, true)], &corners, safe_zone),
+            t3: ZonePolygon::from_walls(&[(&zone_t1_t3_wall_2, false), (&level_t3_back_wall, false), (&t3_t2_wall_1, true)], &corners, safe_zone),
+        };
+
+        let walls = LevelWalls {
+            t1_inner_1,
+            t1_inner_2,
+            t2_t1_inner,
+            t3_t2_inner,
+            outer_wall,
+            t1_upper_back_wall: level_t1_upper_back_wall,
+            t1_lower_back_wall: level_t1_lower_back_wall,
+            t2_back_wall: level_t2_back_wall,
+            t3_back_wall: level_t3_back_wall,
+        };
+
+        return (walls, zones, paths);
     }
 
     pub fn close_walls(&mut self, lower: &WallSection, upper: &WallSection) -> WallSection {
@@ -607,16 +667,14 @@ impl Generator {
         return wall_closing;
     }
 
-    pub fn generate_roads(&mut self, center_x: f32, center_y: f32, width: f32, height: f32, color: u32, alpha: f32) {
+    pub fn generate_roads(&mut self, center_x: f32, center_y: f32, width: f32, height: f32) -> Vec<RoadSegment> {
         let mut point_map = HashMap::new();
 
         let max = width.max(height);
         for angle in 0..200 {
             let angle = (angle as f32 / 200.0) * PI * 2.0;
-            println!("{:.32?}", angle);
-
             let point = [angle.cos() * max, angle.sin() * max];
-            let (point, distance) = MathHelper::distance_to_ellipse(center_x, center_y, width / 2.0, height / 2.0, &point);
+            let (point, _distance) = MathHelper::distance_to_ellipse(center_x, center_y, width / 2.0, height / 2.0, &point);
             let point = [
                 (point[0] / TEXTURE_WIDTH as f32) as i32,
                 (point[1] / TEXTURE_HEIGHT as f32) as i32,
@@ -631,11 +689,9 @@ impl Generator {
             return first.0.partial_cmp(&second.0).unwrap();
         });
 
-        println!("sorted tiles {:?}", sorted_tiles);
-
         // find the first grass tile to start on, to ensure no roads are cut in half
         let mut start_index = 0;
-        for (index, (angle, tile)) in sorted_tiles.iter().enumerate() {
+        for (index, (_angle, tile)) in sorted_tiles.iter().enumerate() {
             if self.is_tile_grass(tile[0], tile[1]) {
                 start_index = index;
                 break;
@@ -648,7 +704,7 @@ impl Generator {
         // less than or equal so it will do +1 allowing it to finish off the last road
         for i in 0..=sorted_tiles.len() {
             let index = (start_index + i) % sorted_tiles.len();
-            let (angle, tile) = sorted_tiles[index];
+            let (_angle, tile) = sorted_tiles[index];
 
             if !self.is_tile_grass(tile[0], tile[1]) {
                 if start_road_segment.is_none() {
@@ -692,21 +748,116 @@ impl Generator {
 
                 // this is bad
 
-                println!("creating at {:?} {:?} {:?}", center, start_angle.0, end_angle.0);
-                road_segments.push(RoadSegment::create(self, center, start_angle.0, end_angle.0, d));
+                // labeled by where the road leaves the safe zone so other roads coming and going don't change it
+                let label = format!("road {} {}", start_tile[0], start_tile[1]);
+                road_segments.push((label, RoadSegment::create(self, center, start_angle.0, end_angle.0, d)));
 
                 start_road_segment = None;
                 end_road_segment = None;
             }
         }
 
-        for road_segment in &mut road_segments {
-            let children = road_segment.extend(self, 40000.0);
-            road_segment.render(self);
+        let road_length = self.config.road_length;
+        let mut roads = Vec::new();
+        for (label, mut road_segment) in road_segments {
+            let children = self.with_stream(&label, |generator| road_segment.extend(generator, road_length));
+            if road_segment.is_drawable() {
+                roads.push(road_segment);
+            }
             for child in children {
-                child.render(self);
+                roads.push(child);
+            }
+        }
+
+        return roads;
+    }
+
+    // plants a road through every path that none of the roads go through yet, so no path leads nowhere
+    fn generate_path_roads(&mut self, roads: &mut Vec<RoadSegment>, safe_zone: &SafeZone) {
+        let paths = self.paths.clone();
+        for (index, path) in paths.iter().enumerate() {
+            if Generator::is_path_used(roads, path) {
+                continue;
+            }
+
+            let route = match self.find_path_route(roads, path, safe_zone) {
+                Some(route) => route,
+                None => continue,
+            };
+
+            // carry on straight out of the path into the tier beyond it
+            let end = route[route.len() - 1];
+            let angle = (end[1] - path.center[1]).atan2(end[0] - path.center[0]);
+            let mut road = RoadSegment::along_route(&route, angle, PI / 4.0, path.width / 4.0);
+
+            let road_length = self.config.path_road_length;
+            let children = self.with_stream(&format!("path road {}", index), |generator| road.extend(generator, road_length));
+            if road.is_drawable() {
+                roads.push(road);
+            }
+            for child in children {
+                roads.push(child);
+            }
+        }
+    }
+
+    fn is_path_used(roads: &[RoadSegment], path: &WallPath) -> bool {
+        for road in roads {
+            for pair in road.points.windows(2) {
+                let line = [[pair[0].x, pair[0].y], [pair[1].x, pair[1].y]];
+                let (_nearest, distance) = MathHelper::distance_to_line_segment(&line, &path.center);
+                if distance <= path.width / 2.0 {
+                    return true;
+                }
+            }
+        }
+
+        return false;
+    }
+
+    // the shortest clear line from a road or the safe zone edge to either side of the path, then through it and out the other side
+    fn find_path_route(&self, roads: &[RoadSegment], path: &WallPath, safe_zone: &SafeZone) -> Option<Vec<[f32; 2]>> {
+        let clearance = path.width / 2.0;
+
+        let mut nearest: Option<(f32, [f32; 2], [f32; 2])> = None;
+        for side in [-1.0, 1.0] {
+            let heading = [path.direction[0] * side, path.direction[1] * side];
+            let entrance = path.get_exit([-heading[0], -heading[1]], clearance);
+            if self.intersect_walls(entrance, path.get_exit(heading, clearance)).is_some() {
+                continue;
+            }
+
+            let (edge_point, _distance) = MathHelper::distance_to_ellipse(safe_zone.center[0], safe_zone.center[1], safe_zone.width / 2.0, safe_zone.height / 2.0, &entrance);
+            let mut starts: Vec<(f32, [f32; 2])> = roads.iter()
+                .flat_map(|road| road.points.iter().map(|point| [point.x, point.y]))
+                .chain(std::iter::once(edge_point))
+                .filter(|point| point[0].is_finite() && point[1].is_finite())
+                .map(|point| (((point[0] - entrance[0]).powi(2) + (point[1] - entrance[1]).powi(2)).sqrt(), point))
+                .collect();
+            starts.sort_by(|first, second| first.0.total_cmp(&second.0));
+
+            for (distance, start) in starts {
+                if nearest.is_some_and(|(nearest_distance, _start, _heading)| distance >= nearest_distance) {
+                    break;
+                }
+
+                if self.intersect_walls(start, entrance).is_none() {
+                    nearest = Some((distance, start, heading));
+                    break;
+                }
             }
         }
+
+        let (_distance, start, heading) = nearest?;
+        let entrance = path.get_exit([-heading[0], -heading[1]], clearance);
+        let exit = path.get_exit(heading, clearance);
+
+        let mut route = vec![start];
+        route.append(&mut MathHelper::subdivide_line(start, entrance, ROUTE_STEP_LENGTH));
+        route.append(&mut MathHelper::subdivide_line(entrance, path.center, ROUTE_STEP_LENGTH));
+        route.append(&mut MathHelper::subdivide_line(path.center, exit, ROUTE_STEP_LENGTH));
+
+        return Some(route);
     }
 
     // fn fill_wall(&mut self, wall: &mut WallSection, length: f32, angle: f32, desired_angle: f32, desired_angle_strength: f32, distance_wall: Option<&WallSection>) {
@@ -726,7 +877,7 @@ impl Generator {
     //
     //     wall.add_point(&point);
     //     while current_wall_length < length {
-    //         let angle_mod = self.get_perlin_value(point[0] - 10240.0, point[1] - 10240.0, 1.0);
+    //         let angle_mod = self.get_noise_value(NoiseLayer::RoadDrift, point[0], point[1], 1.0);
     //         current_angle += angle_mod * PI * 0.1;
     //
     //         let desired_difference = MathHelper::radians_between_angles(current_angle, desired_angle);
@@ -768,8 +919,8 @@ impl Generator {
     fn create_road_bool_tiles(&mut self) -> HashMap<i32, HashMap<i32, bool>> {
         let mut tiles: HashMap<i32, HashMap<i32, bool>> = HashMap::new();
 
-        let width = (SAFE_ZONE_WIDTH / TEXTURE_WIDTH as f32) as i32;
-        let height = (SAFE_ZONE_HEIGHT / TEXTURE_HEIGHT as f32) as i32;
+        let width = (self.config.get_safe_zone_width() / TEXTURE_WIDTH as f32) as i32;
+        let height = (self.config.get_safe_zone_height() / TEXTURE_HEIGHT as f32) as i32;
 
         let center_x = 0;
         let center_y = 0;
@@ -798,7 +949,7 @@ impl Generator {
                         let position_x = (x * TEXTURE_WIDTH as i32 + TEXTURE_WIDTH as i32 / 2) as f32;
                         let position_y = (y * TEXTURE_HEIGHT as i32 + TEXTURE_HEIGHT as i32 / 2) as f32;
 
-                        let (is_road, road_strength) = self.sample_road(position_x, position_y);
+                        let (is_road, _road_strength) = self.sample_road(position_x, position_y);
                         if is_road {
                             // gravel
                             tiles.insert(y, true);
@@ -820,9 +971,9 @@ impl Generator {
 
     pub fn sample_road(&mut self, x: f32, y: f32) -> (bool, f32) {
         let distance = x * x + y * y;
-        let scale = (2.0 as f32).max(10000000.0 / distance);
-        let noise1 = self.get_perlin_value(x, y, scale);
-        let noise2 = self.get_perlin_value(x + APPROX_WIDTH, x + APPROX_HEIGHT, scale);
+        let scale = 2.0_f32.max(10000000.0 / distance);
+        let noise1 = self.get_noise_value(NoiseLayer::RoadTiles, x, y, scale);
+        let noise2 = self.get_noise_value(NoiseLayer::RoadTilesSecondary, x, y, scale);
         if noise1.abs() < 0.05 || noise2.abs() < 0.05 {
             return (true, noise1.abs().min(noise2.abs()));
         }
@@ -830,142 +981,256 @@ impl Generator {
         return (false, noise1.abs().min(noise2.abs()));
     }
 
-    pub fn draw_tile(&mut self, x: i32, y: i32, color: u32, alpha: f32) {
-        let center_x = (LEVEL_WIDTH / 2) as i32;
-        let center_y = (LEVEL_HEIGHT / 2) as i32;
-
-        // dont draw outside bounds, itll crash at casting
-        if (x + center_x as i32) < 0 || (y + center_y as i32) < 0 {
-            return;
+    fn is_tile_grass(&self, x: i32, y: i32) -> bool {
+        if let Some(tile_index) = Generator::grid_index((x + LEVEL_WIDTH as i32 / 2) as u32, (y + LEVEL_HEIGHT as i32 / 2) as u32) {
+            return self.tiles[tile_index] == Tile::Grass;
         }
 
-        let x = (center_x + x) as u32;
-        let y = (center_y + y) as u32;
-
-        self.draw_rect(x, y, 1, 1, color, alpha);
+        return false;
     }
 
-    pub fn draw_oval(&mut self, center_x: f32, center_y: f32, width: f32, height: f32, color: u32, alpha: f32) {
-        let center_x = (center_x / TEXTURE_WIDTH as f32) as i32;
-        let center_y = (center_y / TEXTURE_HEIGHT as f32) as i32;
+    fn grid_index(x: u32, y: u32) -> Option<usize> {
+        let index = y * LEVEL_WIDTH as u32 + x;
+        if index >= (LEVEL_WIDTH * LEVEL_HEIGHT) as u32 {
+            return None;
+        }
 
-        let width = (width / TEXTURE_WIDTH as f32) as i32;
-        let height = (height / TEXTURE_HEIGHT as f32) as i32;
+        return Some(index as usize);
+    }
 
-        for x in (center_x - width as i32 / 2)..(center_x + width as i32 / 2) {
-            for y in (center_y - height as i32 / 2)..(center_y + height as i32 / 2) {
-                let dx = (x - center_x) as f32 / (width as f32 / 2.0);
-                let dy = (y - center_y) as f32 / (height as f32 / 2.0);
-                let d2 = dx * dx + dy * dy;
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> f32 {
+        return self.random.next_f32();
+    }
 
-                if d2 <= 1.0 {
-                    self.draw_tile(x, y, color, alpha);
-                }
-            }
-        }
+    // runs a stage with its own random stream derived from the seed and the label
+    // so adding or removing draws in one stage doesn't reshuffle every stage after it
+    pub fn with_stream<T>(&mut self, label: &str, stage: impl FnOnce(&mut Generator) -> T) -> T {
+        let stream = Random::create(self.seed).fork(label);
+        let previous = std::mem::replace(&mut self.random, stream);
+        let result = stage(self);
+        self.random = previous;
+
+        return result;
     }
 
-    pub fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, color: u32, alpha: f32) {
-        let x1 = (x1 / TEXTURE_WIDTH as f32) as i32;
-        let y1 = (y1 / TEXTURE_HEIGHT as f32) as i32;
-        let x2 = (x2 / TEXTURE_WIDTH as f32) as i32;
-        let y2 = (y2 / TEXTURE_HEIGHT as f32) as i32;
+    // the layer's own noise field with the fractal and warp settings from the config
+    // noise_detail / scale is the frequency, and y is stretched by 1 / 0.75 to match the screen
+    pub fn get_noise_value(&self, layer: NoiseLayer, x: f32, y: f32, scale: f32) -> f32 {
+        let config = &self.config;
+        let (fractal, warp) = match layer {
+            NoiseLayer::RoadTiles | NoiseLayer::RoadTilesSecondary => (config.road_tile_noise, DomainWarp::default()),
+            NoiseLayer::RoadDrift => (config.road_drift_noise, config.road_drift_warp),
+            NoiseLayer::RoadThickness | NoiseLayer::RoadThicknessSecondary => (config.road_thickness_noise, DomainWarp::default()),
+            NoiseLayer::WallDrift => (config.wall_drift_noise, DomainWarp::default()),
+            NoiseLayer::WallWobble => (config.wall_wobble_noise, config.wall_wobble_warp),
+            NoiseLayer::WallThickness | NoiseLayer::WallThicknessSecondary => (config.wall_thickness_noise, DomainWarp::default()),
+        };
 
-        for (x, y) in Bresenham::new((x1 as i32, y1 as i32), (x2 as i32, y2 as i32)) {
-            self.draw_tile(x, y, color, alpha);
-        }
+        let noise_detail = config.noise_detail;
+        let layer = layer as usize;
+        return warp.sample(&self.noise_layers[layer], &self.warp_layers[layer], &fractal, x * (noise_detail / scale), y * (noise_detail / scale) / 0.75);
     }
 
-    pub fn draw_line_thickness(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32, color: u32, alpha: f32) {
-        let aabb = [
-            [((x1.min(x2) - radius) / TEXTURE_WIDTH as f32).floor() as i32, ((y1.min(y2) - radius) / TEXTURE_HEIGHT as f32).floor() as i32],
-            [((x1.max(x2) + radius) / TEXTURE_WIDTH as f32).ceil() as i32, ((y1.max(y2) + radius) / TEXTURE_HEIGHT as f32).ceil() as i32],
-        ];
+    pub fn get_config(&self) -> &GeneratorConfig {
+        return &self.config;
+    }
 
-        for x in aabb[0][0]..=aabb[1][0] {
-            for y in aabb[0][1]..=aabb[1][1] {
-                let cx = x as f32 * TEXTURE_WIDTH as f32 + TEXTURE_WIDTH as f32 / 2.0;
-                let cy = y as f32 * TEXTURE_HEIGHT as f32 + TEXTURE_HEIGHT as f32 / 2.0;
+    pub fn get_paths(&self) -> &[WallPath] {
+        return &self.paths;
+    }
 
-                if MathHelper::is_point_inside_ellipse([cx, cy], [0.0, 0.0], [SAFE_ZONE_WIDTH - TEXTURE_WIDTH as f32 * 2.0, SAFE_ZONE_HEIGHT - TEXTURE_HEIGHT as f32 * 2.0]) {
-                    continue;
-                }
+    // where the line first hits a wall and the wall segment it hit, always none before the walls are generated
+    pub fn intersect_walls(&self, from: [f32; 2], to: [f32; 2]) -> Option<([f32; 2], [[f32; 2]; 2])> {
+        return self.wall_index.as_ref().and_then(|index| index.intersect_wall([from, to]));
+    }
 
-                let (point, distance) = MathHelper::distance_to_line_segment(&[[x1, y1], [x2, y2]], &[cx, cy]);
-                if distance - 0.000001 <= radius {
-                    self.draw_tile(x, y, color, alpha);
-                }
+    // every path with its center within max_distance and its index, closest first
+    pub fn get_nearby_paths(&self, point: [f32; 2], max_distance: f32) -> Vec<(usize, WallPath)> {
+        let mut paths = Vec::new();
+        for (index, path) in self.paths.iter().enumerate() {
+            let dx = path.center[0] - point[0];
+            let dy = path.center[1] - point[1];
+            let distance = (dx * dx + dy * dy).sqrt();
+            if distance <= max_distance {
+                paths.push((distance, index, *path));
             }
         }
+
+        paths.sort_by(|first, second| first.0.total_cmp(&second.0));
+
+        return paths.into_iter().map(|(_distance, index, path)| (index, path)).collect();
     }
+}
 
-    fn is_tile_grass(&self, x: i32, y: i32) -> bool {
-        if let Some(tile_index) = Generator::grid_index((x + LEVEL_WIDTH as i32 / 2) as u32, (y + LEVEL_HEIGHT as i32 / 2) as u32) {
-            return self.grass[tile_index];
-        }
+impl Default for Generator {
+    fn default() -> Self {
+        // TODO cursed seed to try before finalizing: 1835892476
+        let seed: u64 = rand::random();
 
-        return false;
+        return Generator::with_seed(seed);
     }
+}
 
-    fn draw_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: u32, alpha: f32) {
-        for offset_y in 0..height {
-            let y = y + offset_y;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RoadWallMode;
+    use crate::level::Zone;
 
-            for offset_x in 0..width {
-                let x = x + offset_x;
+    #[test]
+    fn streams_do_not_depend_on_earlier_draws() {
+        let mut generator = Generator::with_seed(9);
+        let first = generator.with_stream("roads", |generator| generator.next());
 
-                if let Some(index) = Generator::grid_index(x, y) {
-                    let orig_color_r = (self.pixels[index] & 0xff000000) >> 24;
-                    let orig_color_g = (self.pixels[index] & 0x00ff0000) >> 16;
-                    let orig_color_b = (self.pixels[index] & 0x0000ff00) >> 8;
+        generator.next();
+        generator.next();
+        let after_draws = generator.with_stream("roads", |generator| generator.next());
+        let other_stream = generator.with_stream("walls", |generator| generator.next());
 
-                    let color_r = (color & 0xff0000) >> 16;
-                    let color_g = (color & 0x00ff00) >> 8;
-                    let color_b = color & 0x0000ff;
+        assert_eq!(first, after_draws);
+        assert_ne!(first, other_stream);
+    }
 
-                    let new_color_r = (orig_color_r as f32 * (1.0 - alpha) + color_r as f32 * alpha).round() as u32;
-                    let new_color_g = (orig_color_g as f32 * (1.0 - alpha) + color_g as f32 * alpha).round() as u32;
-                    let new_color_b = (orig_color_b as f32 * (1.0 - alpha) + color_b as f32 * alpha).round() as u32;
+    #[test]
+    fn streams_restore_the_main_random() {
+        let mut generator = Generator::with_seed(9);
+        let mut other = Generator::with_seed(9);
 
-                    self.pixels[index] = (new_color_r << 24) | (new_color_g << 16) | (new_color_b << 8) | 0x000000ff;
-                }
-            }
-        }
+        generator.with_stream("roads", |generator| {
+            generator.next();
+            generator.next();
+        });
+        assert_eq!(generator.next(), other.next());
     }
 
-    fn grid_index(x: u32, y: u32) -> Option<usize> {
-        let index = y * LEVEL_WIDTH as u32 + x;
-        if index < 0 || index >= (LEVEL_WIDTH * LEVEL_HEIGHT) as u32 {
-            return None;
+    #[test]
+    fn noise_layers_are_independent() {
+        let mut generator = Generator::with_seed(4);
+        generator.generate();
+
+        let point = [1234.0, -5678.0];
+        let values: Vec<f32> = NoiseLayer::ALL.iter().map(|layer| generator.get_noise_value(*layer, point[0], point[1], 1.0)).collect();
+        for (index, value) in values.iter().enumerate() {
+            assert!((-1.0..=1.0).contains(value));
+            assert!(values[(index + 1)..].iter().all(|other| other != value));
         }
 
-        return Some(index as usize);
+        let mut other = Generator::with_seed(4);
+        other.generate();
+        assert_eq!(other.get_noise_value(NoiseLayer::WallWobble, point[0], point[1], 1.0), values[NoiseLayer::WallWobble as usize]);
     }
 
-    pub fn next(&mut self) -> f32 {
-        return self.random.next();
+    #[test]
+    fn same_seed_same_level() {
+        let level = Generator::with_seed(11).generate();
+        let other = Generator::with_seed(11).generate();
+
+        assert_eq!(level.tiles, other.tiles);
+        assert_eq!(level.walls.outer_wall.lines, other.walls.outer_wall.lines);
+        assert_eq!(level.roads.len(), other.roads.len());
+        for (road, other_road) in level.roads.iter().zip(&other.roads) {
+            assert_eq!(road.points, other_road.points);
+        }
     }
 
-    pub fn get_perlin_value(&mut self, x: f32, y: f32, scale: f32) -> f32 {
-        return self.noise.perlin2(x * (NOISE_DETAIL / scale), y * (NOISE_DETAIL / scale) / 0.75);
+    fn crosses_a_wall(level: &Level) -> bool {
+        let index = LevelIndex::create(&level.walls, &level.zones);
+        return level.roads.iter().any(|road| {
+            return road.points.windows(2).any(|pair| index.intersect_wall([[pair[0].x, pair[0].y], [pair[1].x, pair[1].y]]).is_some());
+        });
     }
-}
 
-impl Default for Generator {
-    fn default() -> Self {
-        let seed: u32 = rand::random();
-        let seed: u32 = 1991568964;
+    // the tiers are walkable, the inside of the divider walls and everything past the outer wall isn't
+    // the inner t1 walls sit inside their tier, so both sides of them are t1, that includes their ends
+    // that are joined into the outer wall between the two t1 back walls
+    // a divider's gap and the start of the tiers at the safe zone follow their own rules and are left out
+    #[test]
+    fn zones_follow_the_final_walls() {
+        const OFFSET: f32 = 2.0;
+
+        for seed in 0..4 {
+            let mut generator = Generator::with_seed(seed);
+            let level = generator.generate();
+            let safe_zone = level.safe_zone;
+            let outline = |wall: &WallSection| ZonePolygon {points: wall.lines.clone()};
+            let solid = [outline(&level.walls.t2_t1_inner), outline(&level.walls.t3_t2_inner)];
+            let outer = outline(&level.walls.outer_wall);
+
+            // rounding keeps every point of the back walls
+            let outer_index = |point: &[f32; 2]| level.walls.outer_wall.lines.iter().position(|outer_point| outer_point == point);
+            let t1_ends_start = level.walls.t1_lower_back_wall.lines.iter().filter_map(outer_index).max().unwrap();
+            let t1_ends_end = level.walls.t1_upper_back_wall.lines.iter().filter_map(outer_index).min().unwrap();
+            assert!(t1_ends_start < t1_ends_end);
+
+            let walls = [
+                (&level.walls.t1_inner_1, 0..usize::MAX),
+                (&level.walls.t1_inner_2, 0..usize::MAX),
+                (&level.walls.t2_t1_inner, 0..0),
+                (&level.walls.t3_t2_inner, 0..0),
+                (&level.walls.outer_wall, t1_ends_start..t1_ends_end),
+            ];
+            for (wall, in_t1) in walls {
+                let count = wall.lines.len();
+                for index in 0..count {
+                    let point = wall.lines[index];
+                    let next_point = wall.lines[(index + 1) % count];
+                    let direction = match MathHelper::normalize([next_point[0] - point[0], next_point[1] - point[1]]) {
+                        Some(direction) => direction,
+                        None => continue,
+                    };
+                    let middle = [(point[0] + next_point[0]) / 2.0, (point[1] + next_point[1]) / 2.0];
+
+                    let near_safe_zone = MathHelper::is_point_inside_ellipse(middle, safe_zone.center, [safe_zone.width * 1.5, safe_zone.height * 1.5]);
+                    // the faces around a gap can be wider than the gap and the segments cut by it reach further still
+                    let near_path = generator.get_paths().iter().any(|path| MathHelper::distance(&path.center, &middle) < path.depth + path.width * 2.0);
+                    if near_safe_zone || near_path {
+                        continue;
+                    }
 
-        // TODO cursed seed to try before finalizing
-        // let seed: u32 = 1835892476;
-        println!("SEED {:?}", seed);
+                    for side in [-1.0, 1.0] {
+                        let sample = [middle[0] - direction[1] * OFFSET * side, middle[1] + direction[0] * OFFSET * side];
+                        let zone = level.zone_at(sample[0], sample[1]);
+                        if in_t1.contains(&index) {
+                            assert_eq!(zone, Zone::T1, "seed {} {:?} by the inner t1 walls", seed, sample);
+                            continue;
+                        }
 
-        return Generator {
-            pixels: vec![0; (LEVEL_WIDTH * LEVEL_HEIGHT) as usize],
-            grass: vec![true; (LEVEL_WIDTH * LEVEL_HEIGHT) as usize],
-            random: Random::create(seed as i64),
-            noise: Perlin::default(),
-            seed: seed as i64,
-        };
+                        let is_walkable = outer.contains(&sample) && !solid.iter().any(|solid| solid.contains(&sample));
+                        assert_eq!(zone != Zone::Outside, is_walkable, "seed {} {:?} is {:?}", seed, sample, zone);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn roads_stay_out_of_walls_in_every_mode() {
+        for mode in [RoadWallMode::Terminate, RoadWallMode::Deflect, RoadWallMode::RouteToPath] {
+            let config = GeneratorConfig {
+                road_wall_mode: mode,
+                ..GeneratorConfig::default()
+            };
+            let level = Generator::with_config(6, config).generate();
+
+            assert!(!crosses_a_wall(&level), "{:?} roads cross a wall", mode);
+        }
+    }
+
+    #[test]
+    fn every_path_has_a_road_through_it() {
+        let mut generator = Generator::with_seed(8);
+        let level = generator.generate();
+
+        assert!(!generator.get_paths().is_empty());
+        for path in generator.get_paths() {
+            let through = level.roads.iter().any(|road| {
+                return road.points.windows(2).any(|pair| {
+                    let (_nearest, distance) = MathHelper::distance_to_line_segment(&[[pair[0].x, pair[0].y], [pair[1].x, pair[1].y]], &path.center);
+                    return distance < path.width / 2.0;
+                });
+            });
+            assert!(through, "no road through the path at {:?}", path.center);
+        }
     }
 }
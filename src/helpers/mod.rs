@@ -1,2 +1,4 @@
 pub mod wall_section;
-pub mod road_segment;
\ No newline at end of file
+pub mod road_segment;
+pub mod zone_polygon;
+pub mod road_cleanup;
@@ -0,0 +1,446 @@
+use crate::generator::Generator;
+use crate::helpers::road_segment::{Point, RoadSegment};
+use crate::config::GeneratorConfig;
+use crate::level::SafeZone;
+use crate::math_helper::{MathHelper, Segment};
+use crate::road_network::{RoadNetwork, RoadNodeKind, CROSSING_EPSILON, SAFE_ZONE_MARGIN};
+use rstar::{RTree, AABB};
+
+// tidies up the roads once they're grown, a step is skipped when its distance is zero
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RoadCleanup {
+    // parts of roads this close to an earlier road and running the same way are dropped and the rest joined onto it
+    pub merge_distance: f32,
+    // in radians
+    pub merge_angle: f32,
+    // loose road ends this close to another road are joined onto it
+    pub snap_distance: f32,
+    // dead ends shorter than this are extended to a road ahead of them or removed back to the junction they come from
+    pub dead_end_length: f32,
+}
+
+impl RoadCleanup {
+    pub fn from_config(config: &GeneratorConfig) -> Self {
+        return RoadCleanup {
+            merge_distance: config.road_merge_distance,
+            merge_angle: config.road_merge_angle.to_radians(),
+            snap_distance: config.road_snap_distance,
+            dead_end_length: config.road_dead_end_length,
+        };
+    }
+
+    pub fn apply(&self, generator: &Generator, roads: Vec<RoadSegment>, safe_zone: &SafeZone) -> Vec<RoadSegment> {
+        let roads = self.merge_parallel(generator, roads);
+        let roads = self.snap_ends(generator, roads, safe_zone);
+        return self.fix_dead_ends(generator, roads, safe_zone);
+    }
+
+    // roads are compared against the ones before them, so a split's second child is folded into the first
+    fn merge_parallel(&self, generator: &Generator, roads: Vec<RoadSegment>) -> Vec<RoadSegment> {
+        if self.merge_distance <= 0.0 {
+            return roads;
+        }
+
+        let mut merged_roads: Vec<RoadSegment> = Vec::new();
+        let mut lines = RTree::new();
+        for road in roads {
+            let points = &road.points;
+            let parallel: Vec<Option<[f32; 2]>> = (0..points.len()).map(|index| self.find_parallel(generator, &lines, points, index)).collect();
+
+            let mut pieces = Vec::new();
+            if parallel.iter().all(Option::is_none) {
+                pieces.push(road.points);
+            } else {
+                let mut piece: Vec<Point> = Vec::new();
+                for (index, point) in points.iter().enumerate() {
+                    match parallel[index] {
+                        Some(nearest) => {
+                            // finish the piece on the road it runs into, going by this point when cutting straight there hits a wall
+                            if let Some(last) = piece.last() {
+                                let thickness = last.thickness;
+                                if generator.intersect_walls([last.x, last.y], nearest).is_some() {
+                                    piece.push(*point);
+                                }
+                                piece.push(Point {
+                                    x: nearest[0],
+                                    y: nearest[1],
+                                    thickness,
+                                });
+                                pieces.push(std::mem::take(&mut piece));
+                            }
+                        }
+                        None => {
+                            // and start the next one from it the same way
+                            if piece.is_empty() && index > 0 {
+                                if let Some(nearest) = parallel[index - 1] {
+                                    piece.push(Point {
+                                        x: nearest[0],
+                                        y: nearest[1],
+                                        thickness: point.thickness,
+                                    });
+                                    if generator.intersect_walls(nearest, [point.x, point.y]).is_some() {
+                                        piece.push(points[index - 1]);
+                                    }
+                                }
+                            }
+
+                            piece.push(*point);
+                        }
+                    }
+                }
+                pieces.push(piece);
+            }
+
+            for piece in pieces {
+                if piece.len() < 2 {
+                    continue;
+                }
+
+                let road = merged_roads.len();
+                for pair in piece.windows(2) {
+                    lines.insert(Segment::create([pair[0].x, pair[0].y], [pair[1].x, pair[1].y], road));
+                }
+                merged_roads.push(RoadSegment::from_points(piece));
+            }
+        }
+
+        return merged_roads;
+    }
+
+    // the nearest point on an earlier road that the point runs alongside, if there is one
+    fn find_parallel(&self, generator: &Generator, lines: &RTree<Segment<usize>>, points: &[Point], index: usize) -> Option<[f32; 2]> {
+        let point = [points[index].x, points[index].y];
+        let previous = &points[index.saturating_sub(1)];
+        let next = &points[(index + 1).min(points.len() - 1)];
+        let direction = MathHelper::normalize([next.x - previous.x, next.y - previous.y])?;
+
+        // the roads through a wall path are left alone so the path stays reachable
+        let is_in_path = generator.get_paths().iter().any(|path| MathHelper::distance(&path.center, &point) <= path.width / 2.0 + self.merge_distance);
+        if is_in_path {
+            return None;
+        }
+
+        for line in lines.nearest_neighbor_iter(&point) {
+            let (nearest, distance) = MathHelper::distance_to_line_segment(&line.points, &point);
+            if distance > self.merge_distance {
+                break;
+            }
+
+            let line_direction = match MathHelper::normalize([line.points[1][0] - line.points[0][0], line.points[1][1] - line.points[0][1]]) {
+                Some(line_direction) => line_direction,
+                None => continue,
+            };
+
+            // either way along the other road counts
+            let cross = direction[0] * line_direction[1] - direction[1] * line_direction[0];
+            // a road on the other side of a wall only looks close
+            if cross.abs() <= self.merge_angle.sin() && generator.intersect_walls(point, nearest).is_none() {
+                return Some(nearest);
+            }
+        }
+
+        return None;
+    }
+
+    fn snap_ends(&self, generator: &Generator, mut roads: Vec<RoadSegment>, safe_zone: &SafeZone) -> Vec<RoadSegment> {
+        if self.snap_distance <= 0.0 {
+            return roads;
+        }
+
+        let lines = RTree::bulk_load(RoadCleanup::get_lines(&roads));
+        for (road, road_segment) in roads.iter_mut().enumerate() {
+            for is_end in [false, true] {
+                let endpoint = if is_end {road_segment.points[road_segment.points.len() - 1]} else {road_segment.points[0]};
+                let point = [endpoint.x, endpoint.y];
+
+                // roads leaving the safe zone are already joined to it
+                if MathHelper::is_point_inside_ellipse(point, safe_zone.center, [safe_zone.width * SAFE_ZONE_MARGIN, safe_zone.height * SAFE_ZONE_MARGIN]) {
+                    continue;
+                }
+
+                let line = match lines.nearest_neighbor_iter(&point).find(|line| line.data != road) {
+                    Some(line) => line,
+                    None => continue,
+                };
+
+                let (nearest, distance) = MathHelper::distance_to_line_segment(&line.points, &point);
+                if distance <= CROSSING_EPSILON || distance > self.snap_distance {
+                    continue;
+                }
+
+                if generator.intersect_walls(point, nearest).is_some() {
+                    continue;
+                }
+
+                let snapped = Point {
+                    x: nearest[0],
+                    y: nearest[1],
+                    thickness: endpoint.thickness,
+                };
+                if is_end {
+                    road_segment.points.push(snapped);
+                } else {
+                    road_segment.points.insert(0, snapped);
+                }
+            }
+        }
+
+        return roads;
+    }
+
+    fn fix_dead_ends(&self, generator: &Generator, mut roads: Vec<RoadSegment>, safe_zone: &SafeZone) -> Vec<RoadSegment> {
+        if self.dead_end_length <= 0.0 {
+            return roads;
+        }
+
+        let network = RoadNetwork::create(&roads, safe_zone);
+        let lines = RTree::bulk_load(RoadCleanup::get_lines(&roads));
+
+        // how many points to cut off the start and the end of each road and the junction to stop at instead
+        let mut cut_start: Vec<Option<(usize, [f32; 2])>> = vec![None; roads.len()];
+        let mut cut_end: Vec<Option<(usize, [f32; 2])>> = vec![None; roads.len()];
+        let mut removed = vec![false; roads.len()];
+        for (node, road_node) in network.nodes.iter().enumerate() {
+            if road_node.kind != RoadNodeKind::End || network.get_degree(node) != 1 {
+                continue;
+            }
+
+            let (edge, _other) = network.get_neighbors(node)[0];
+            let road_edge = &network.edges[edge];
+            let road = match road_edge.road {
+                Some(road) => road,
+                None => continue,
+            };
+            if road_edge.length >= self.dead_end_length {
+                continue;
+            }
+
+            // edges run from the start of their road to the end
+            let is_end = road_edge.nodes[1] == node;
+            let points = &roads[road].points;
+            let (from, before) = if is_end {
+                (points[points.len() - 1], points[points.len() - 2])
+            } else {
+                (points[0], points[1])
+            };
+
+            if let Some(hit) = self.cast_to_road(generator, &lines, road, [before.x, before.y], [from.x, from.y]) {
+                let extended = Point {
+                    x: hit[0],
+                    y: hit[1],
+                    thickness: from.thickness,
+                };
+                if is_end {
+                    roads[road].points.push(extended);
+                } else {
+                    roads[road].points.insert(0, extended);
+                }
+
+                continue;
+            }
+
+            // the way through a wall path is kept even when it doesn't lead far
+            if RoadCleanup::is_through_path(generator, &road_edge.points) {
+                continue;
+            }
+
+            // a dead end that's the whole road is cut down to a stub and removed below
+            let cut_points = road_edge.points.len() - 1;
+            if is_end {
+                cut_end[road] = Some((cut_points, road_edge.points[0]));
+            } else {
+                cut_start[road] = Some((cut_points, road_edge.points[road_edge.points.len() - 1]));
+            }
+        }
+
+        for (road, road_segment) in roads.iter_mut().enumerate() {
+            if let Some((count, junction)) = cut_end[road] {
+                let keep = road_segment.points.len().saturating_sub(count);
+                road_segment.points.truncate(keep);
+                if let Some(last) = road_segment.points.last() {
+                    let thickness = last.thickness;
+                    road_segment.points.push(Point {
+                        x: junction[0],
+                        y: junction[1],
+                        thickness,
+                    });
+                }
+            }
+
+            if let Some((count, junction)) = cut_start[road] {
+                let count = count.min(road_segment.points.len());
+                road_segment.points.drain(..count);
+                if let Some(first) = road_segment.points.first() {
+                    let thickness = first.thickness;
+                    road_segment.points.insert(0, Point {
+                        x: junction[0],
+                        y: junction[1],
+                        thickness,
+                    });
+                }
+            }
+
+            // whatever is left of a road that was all dead end sits inside the road it came from
+            let is_cut = cut_start[road].is_some() || cut_end[road].is_some();
+            let is_stub = road_segment.points.first().is_none_or(|first| road_segment.get_length() <= CROSSING_EPSILON.max(first.thickness / 2.0));
+            if !road_segment.is_drawable() || (is_cut && is_stub) {
+                removed[road] = true;
+            }
+        }
+
+        return roads.into_iter().zip(removed).filter(|(_road, removed)| !removed).map(|(road, _removed)| road).collect();
+    }
+
+    // where carrying on straight from the end of a road first meets another road, if it does before a wall and within the dead end length
+    fn cast_to_road(&self, generator: &Generator, lines: &RTree<Segment<usize>>, road: usize, before: [f32; 2], from: [f32; 2]) -> Option<[f32; 2]> {
+        let direction = MathHelper::normalize([from[0] - before[0], from[1] - before[1]])?;
+        let to = [from[0] + direction[0] * self.dead_end_length, from[1] + direction[1] * self.dead_end_length];
+        let ray = [from, to];
+
+        let envelope = AABB::from_corners([from[0].min(to[0]), from[1].min(to[1])], [from[0].max(to[0]), from[1].max(to[1])]);
+        let mut nearest: Option<([f32; 2], f32)> = None;
+        for line in lines.locate_in_envelope_intersecting(&envelope) {
+            if line.data == road {
+                continue;
+            }
+
+            let hit = match MathHelper::intersect_line_line(ray, line.points) {
+                Some(hit) => hit,
+                None => continue,
+            };
+
+            let (_nearest, distance) = MathHelper::distance_to_line_segment(&line.points, &hit);
+            if distance > CROSSING_EPSILON {
+                continue;
+            }
+
+            let distance = MathHelper::distance(&hit, &from);
+            if distance > CROSSING_EPSILON && nearest.is_none_or(|(_hit, nearest_distance)| distance < nearest_distance) {
+                nearest = Some((hit, distance));
+            }
+        }
+
+        let (hit, _distance) = nearest?;
+        if generator.intersect_walls(from, hit).is_some() {
+            return None;
+        }
+
+        return Some(hit);
+    }
+
+    fn is_through_path(generator: &Generator, points: &[[f32; 2]]) -> bool {
+        return generator.get_paths().iter().any(|path| {
+            return points.windows(2).any(|pair| {
+                let (_nearest, distance) = MathHelper::distance_to_line_segment(&[pair[0], pair[1]], &path.center);
+                return distance <= path.width / 2.0;
+            });
+        });
+    }
+
+    fn get_lines(roads: &[RoadSegment]) -> Vec<Segment<usize>> {
+        let mut lines = Vec::new();
+        for (road, road_segment) in roads.iter().enumerate() {
+            for pair in road_segment.points.windows(2) {
+                lines.push(Segment::create([pair[0].x, pair[0].y], [pair[1].x, pair[1].y], road));
+            }
+        }
+
+        return lines;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn road(points: &[[f32; 2]], thickness: f32) -> RoadSegment {
+        return RoadSegment::from_points(points.iter().map(|point| Point {x: point[0], y: point[1], thickness}).collect());
+    }
+
+    fn points(roads: &[RoadSegment]) -> Vec<Vec<[f32; 2]>> {
+        return roads.iter().map(|road| road.points.iter().map(|point| [point.x, point.y]).collect()).collect();
+    }
+
+    fn safe_zone() -> SafeZone {
+        return SafeZone {
+            center: [0.0, 0.0],
+            width: 200.0,
+            height: 200.0,
+        };
+    }
+
+    // a generator that hasn't generated anything has no walls or paths in the way
+    fn generator() -> Generator {
+        return Generator::with_seed(0);
+    }
+
+    fn cleanup(merge_distance: f32, snap_distance: f32, dead_end_length: f32) -> RoadCleanup {
+        return RoadCleanup {
+            merge_distance,
+            merge_angle: 20.0_f32.to_radians(),
+            snap_distance,
+            dead_end_length,
+        };
+    }
+
+    // a road with a short dead end on a branch off it, and a spur that is all dead end
+    fn dead_end_roads() -> Vec<RoadSegment> {
+        return vec![
+            road(&[[1000.0, 0.0], [7000.0, 0.0]], 40.0),
+            road(&[[3000.0, 0.0], [3000.0, 3000.0]], 30.0),
+            road(&[[3000.0, 2000.0], [2500.0, 2000.0], [2500.0, 1200.0]], 20.0),
+            road(&[[5000.0, 0.0], [5000.0, 500.0]], 20.0),
+        ];
+    }
+
+    #[test]
+    fn parallel_part_is_merged_into_the_earlier_road() {
+        let roads = vec![
+            road(&[[1000.0, 0.0], [2000.0, 0.0], [3000.0, 0.0], [4000.0, 0.0]], 40.0),
+            road(&[[1000.0, 1000.0], [1500.0, 100.0], [2000.0, 100.0], [2500.0, 100.0], [3000.0, 100.0], [3500.0, 1000.0]], 20.0),
+        ];
+        let roads = cleanup(200.0, 0.0, 0.0).apply(&generator(), roads, &safe_zone());
+
+        assert_eq!(points(&roads), vec![
+            vec![[1000.0, 0.0], [2000.0, 0.0], [3000.0, 0.0], [4000.0, 0.0]],
+            vec![[1000.0, 1000.0], [1500.0, 100.0], [2000.0, 0.0]],
+            vec![[2500.0, 0.0], [3000.0, 100.0], [3500.0, 1000.0]],
+        ]);
+        assert_eq!(roads[1].points[2].thickness, 20.0);
+    }
+
+    #[test]
+    fn near_miss_end_is_snapped_onto_the_road() {
+        let roads = vec![
+            road(&[[1000.0, 0.0], [3000.0, 0.0]], 40.0),
+            road(&[[2000.0, 1000.0], [2000.0, 300.0]], 20.0),
+            road(&[[5000.0, 1000.0], [5000.0, 0.0]], 20.0),
+        ];
+        let roads = cleanup(0.0, 600.0, 0.0).apply(&generator(), roads, &safe_zone());
+
+        // the last road is further from the others than the snap distance
+        assert_eq!(points(&roads), vec![
+            vec![[1000.0, 0.0], [3000.0, 0.0]],
+            vec![[2000.0, 1000.0], [2000.0, 300.0], [2000.0, 0.0]],
+            vec![[5000.0, 1000.0], [5000.0, 0.0]],
+        ]);
+    }
+
+    #[test]
+    fn dead_ends_are_extended_or_cut_back() {
+        let roads = cleanup(0.0, 0.0, 1500.0).apply(&generator(), dead_end_roads(), &safe_zone());
+
+        // the branch carries on down to the first road, the rest of the dead ends go back to their junction
+        assert_eq!(points(&roads), vec![
+            vec![[1000.0, 0.0], [7000.0, 0.0]],
+            vec![[3000.0, 0.0], [3000.0, 2000.0]],
+            vec![[3000.0, 2000.0], [2500.0, 2000.0], [2500.0, 1200.0], [2500.0, 0.0]],
+        ]);
+    }
+
+    #[test]
+    fn zero_distances_skip_every_step() {
+        let roads = cleanup(0.0, 0.0, 0.0).apply(&generator(), dead_end_roads(), &safe_zone());
+        assert_eq!(points(&roads), points(&dead_end_roads()));
+    }
+}
@@ -1,11 +1,20 @@
-use crate::generator::{Generator, TEXTURE_WIDTH, TEXTURE_HEIGHT, SAFE_ZONE_WIDTH, SAFE_ZONE_HEIGHT};
+use crate::generator::{Generator, NoiseLayer, TEXTURE_WIDTH, TEXTURE_HEIGHT};
 use crate::math_helper::MathHelper;
+use crate::canvas::Canvas;
 use std::f32::consts::PI;
+use crate::config::RoadWallMode;
+use serde::{Serialize, Deserialize};
 
+// the longest step a road takes when it's being routed through a path
+pub const ROUTE_STEP_LENGTH: f32 = 1000.0;
+// the least a road stops short of a wall it runs into
+const WALL_CLEARANCE: f32 = 50.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Point {
-    x: f32,
-    y: f32,
-    thickness: f32,
+    pub x: f32,
+    pub y: f32,
+    pub thickness: f32,
 }
 
 pub struct RoadSegment {
@@ -14,23 +23,42 @@ pub struct RoadSegment {
     end_angle: f32,
     thickness: f32,
     angle: f32,
-    points: Vec<Point>
+    pub points: Vec<Point>,
 }
 
 impl RoadSegment {
-    pub fn render(&self, generator: &mut Generator) {
-        for index in 0..(self.points.len() - 1) {
+    pub fn render(&self, canvas: &mut Canvas) {
+        for index in 0..self.points.len().saturating_sub(1) {
             let point = &self.points[index];
             let next_point = &self.points[index + 1];
 
             // generator.draw_line(point.x, point.y, next_point.x, next_point.y, 0xffffbb, 1.0);
-            generator.draw_line_thickness(point.x, point.y, next_point.x, next_point.y, point.thickness / 2.0, 0xffffbb, 1.0);
+            canvas.draw_line_thickness(point.x, point.y, next_point.x, next_point.y, point.thickness / 2.0, 0xffffbb, 1.0);
         }
         // let (edge_point, distance) = MathHelper::distance_to_ellipse(0.0, 0.0, SAFE_ZONE_WIDTH / 2.0, SAFE_ZONE_HEIGHT / 2.0, &self.start_point);
         // // generator.draw_line(self.point[0], self.point[1], self.point[0] + self.angle.cos() * 1000.0, self.point[1] + self.angle.sin() * 1000.0, 0x00ffff, 1.0);
         // generator.draw_line(edge_point[0], edge_point[1], edge_point[0] + self.angle.cos() * 1000.0, edge_point[1] + self.angle.sin() * 1000.0, 0x00ffff, 1.0);
     }
 
+    // at least one segment, a road that stopped before its first step is only its start point
+    pub fn is_drawable(&self) -> bool {
+        return self.points.len() > 1;
+    }
+
+    pub fn get_length(&self) -> f32 {
+        let mut length = 0.0;
+        for index in 0..self.points.len().saturating_sub(1) {
+            let point = &self.points[index];
+            let next_point = &self.points[index + 1];
+
+            let dx = next_point.x - point.x;
+            let dy = next_point.y - point.y;
+            length += (dx * dx + dy * dy).sqrt();
+        }
+
+        return length;
+    }
+
     pub fn extend(&mut self, generator: &mut Generator, length: f32) -> Vec<RoadSegment> {
         let mut return_segments = Vec::new();
 
@@ -48,6 +76,10 @@ impl RoadSegment {
 
         let mut split_chance = 0.0;
 
+        let wall_mode = generator.get_config().road_wall_mode;
+        // a road only goes through each path once so it can't bounce back and forth through the same one
+        let mut used_paths = Vec::new();
+
         while distance < length {
             let next_segment_length = 250.0 + 850.0 * generator.next();
 
@@ -55,8 +87,74 @@ impl RoadSegment {
             // rounded angles aren't necessary here, but it leads to more interesting roads
             // because they tend to not overlap exactly and spread out more
             let real_angle = MathHelper::round_to_interval(angle, PI / 8.0);
-            point[0] += real_angle.cos() * next_segment_length;
-            point[1] += real_angle.sin() * next_segment_length;
+            let heading = [real_angle.cos(), real_angle.sin()];
+            let mut next_point = [
+                point[0] + heading[0] * next_segment_length,
+                point[1] + heading[1] * next_segment_length,
+            ];
+
+            if let Some((hit, wall)) = generator.intersect_walls(point, next_point) {
+                let route = if wall_mode == RoadWallMode::RouteToPath {
+                    RoadSegment::find_route(generator, point, heading, &used_paths)
+                } else {
+                    None
+                };
+
+                if let Some((path_index, route)) = route {
+                    used_paths.push(path_index);
+
+                    let mut previous_point = point;
+                    for route_point in route {
+                        let dx = route_point[0] - point[0];
+                        let dy = route_point[1] - point[1];
+                        distance += (dx * dx + dy * dy).sqrt();
+
+                        previous_point = point;
+                        point = route_point;
+                        self.points.push(Point {
+                            x: point[0],
+                            y: point[1],
+                            thickness,
+                        });
+                    }
+
+                    // carry on the way the path goes instead of turning back towards the wall
+                    angle = (point[1] - previous_point[1]).atan2(point[0] - previous_point[0]);
+                    self.angle = angle;
+                    continue;
+                }
+
+                let deflected = if wall_mode == RoadWallMode::Terminate {
+                    None
+                } else {
+                    RoadSegment::deflect(generator, point, heading, wall, next_segment_length)
+                };
+
+                match deflected {
+                    Some((deflected_angle, deflected_point)) => {
+                        angle = deflected_angle;
+                        next_point = deflected_point;
+                    }
+                    None => {
+                        // stop just short of the wall
+                        let dx = hit[0] - point[0];
+                        let dy = hit[1] - point[1];
+                        let hit_distance = (dx * dx + dy * dy).sqrt();
+                        let end_distance = hit_distance - (thickness / 2.0).max(WALL_CLEARANCE);
+                        if end_distance > 1.0 {
+                            self.points.push(Point {
+                                x: point[0] + heading[0] * end_distance,
+                                y: point[1] + heading[1] * end_distance,
+                                thickness,
+                            });
+                        }
+
+                        break;
+                    }
+                }
+            }
+
+            point = next_point;
             distance += next_segment_length;
 
             if distance >= length {
@@ -74,29 +172,44 @@ impl RoadSegment {
             split_chance += (thickness.sqrt() - 12.0).max(0.0) / 18.0 * generator.next();
             let split = split_chance > 1.0;
             if split {
-                let point1 = [
+                let mut point1 = [
                     point[0] - (angle + PI / 2.0).cos() * thickness / 4.0,
                     point[1] - (angle + PI / 2.0).sin() * thickness / 4.0,
                 ];
-                let point2 = [
+                let mut point2 = [
                     point[0] + (angle + PI / 2.0).cos() * thickness / 4.0,
                     point[1] + (angle + PI / 2.0).sin() * thickness / 4.0,
                 ];
 
+                // don't start a branch on the other side of a wall
+                if generator.intersect_walls(point, point1).is_some() {
+                    point1 = point;
+                }
+                if generator.intersect_walls(point, point2).is_some() {
+                    point2 = point;
+                }
+
                 let angle_diff = MathHelper::radians_between_angles(self.start_angle, self.end_angle);
 
-                let mut segment1 = RoadSegment::create(generator, point1, angle - angle_diff * (0.6 + generator.next() * 0.5), angle, thickness / 1.2);
-                let mut segment2 = RoadSegment::create(generator, point2, angle, angle + angle_diff * (0.6 + generator.next() * 0.5), thickness / 1.2);
+                let spread1 = 0.6 + generator.next() * 0.5;
+                let mut segment1 = RoadSegment::create(generator, point1, angle - angle_diff * spread1, angle, thickness / 1.2);
+                let spread2 = 0.6 + generator.next() * 0.5;
+                let mut segment2 = RoadSegment::create(generator, point2, angle, angle + angle_diff * spread2, thickness / 1.2);
                 // let mut segment1 = RoadSegment::create(generator, point1, self.start_angle, self.end_angle, thickness / 1.5);
                 // let mut segment2 = RoadSegment::create(generator, point2, self.start_angle, self.end_angle, thickness / 1.5);
 
+                // a child that stops before its first step has nothing to draw
                 let children1 = segment1.extend(generator, length - distance);
-                return_segments.push(segment1);
+                if segment1.is_drawable() {
+                    return_segments.push(segment1);
+                }
                 for segment in children1 {
                     return_segments.push(segment);
                 }
                 let children2 = segment2.extend(generator, length - distance);
-                return_segments.push(segment2);
+                if segment2.is_drawable() {
+                    return_segments.push(segment2);
+                }
                 for segment in children2 {
                     return_segments.push(segment);
                 }
@@ -135,17 +248,17 @@ impl RoadSegment {
             }*/
 
             // same logic as walls so they kind of bend the same way
-            let angle_mod = generator.get_perlin_value(point[0] - 10240.0, point[1] - 10240.0, 1.0);
+            let angle_mod = generator.get_noise_value(NoiseLayer::RoadDrift, point[0], point[1], 1.0);
             angle += angle_mod * PI * 0.1;
 
             let desired_difference = MathHelper::radians_between_angles(angle, self.angle);
             angle += desired_difference * 0.02;
 
-            let perlin1 = (generator.get_perlin_value(point[0] + 4532.0, point[1] + 7546.0, 10.0) + 1.0) / 2.0;
-            let perlin2 = (generator.get_perlin_value(point[0] + 85623.0, point[1] + 68572.0, 10.0) + 1.0) / 2.0;
+            let perlin1 = (generator.get_noise_value(NoiseLayer::RoadThickness, point[0], point[1], 10.0) + 1.0) / 2.0;
+            let perlin2 = (generator.get_noise_value(NoiseLayer::RoadThicknessSecondary, point[0], point[1], 10.0) + 1.0) / 2.0;
             let perlin = perlin1 * perlin2;
             let thickness_mod = perlin * 2.0 + 0.2;
-            thickness = thickness * thickness_mod;
+            thickness *= thickness_mod;
             thickness = thickness.max(20.0);
 
             let dthick = self.thickness - thickness;
@@ -154,11 +267,93 @@ impl RoadSegment {
 
         return return_segments;
     }
+
+    // the points from here through the closest clear path ahead of the road and out the other side
+    fn find_route(generator: &Generator, point: [f32; 2], heading: [f32; 2], used_paths: &[usize]) -> Option<(usize, Vec<[f32; 2]>)> {
+        let search_distance = generator.get_config().road_path_search_distance;
+        for (index, path) in generator.get_nearby_paths(point, search_distance) {
+            if used_paths.contains(&index) {
+                continue;
+            }
+
+            // turning around to reach a path behind the road looks wrong
+            let to_center = [path.center[0] - point[0], path.center[1] - point[1]];
+            if to_center[0] * heading[0] + to_center[1] * heading[1] < 0.0 {
+                continue;
+            }
+
+            let exit = path.get_exit(to_center, path.width / 2.0);
+            if generator.intersect_walls(point, path.center).is_some() || generator.intersect_walls(path.center, exit).is_some() {
+                continue;
+            }
+
+            let mut route = MathHelper::subdivide_line(point, path.center, ROUTE_STEP_LENGTH);
+            route.append(&mut MathHelper::subdivide_line(path.center, exit, ROUTE_STEP_LENGTH));
+
+            return Some((index, route));
+        }
+
+        return None;
+    }
+
+    // the angle along the wall closest to the road's heading and where a step that way ends, none if that still hits a wall
+    fn deflect(generator: &Generator, point: [f32; 2], heading: [f32; 2], wall: [[f32; 2]; 2], step_length: f32) -> Option<(f32, [f32; 2])> {
+        let mut dx = wall[1][0] - wall[0][0];
+        let mut dy = wall[1][1] - wall[0][1];
+        if dx * heading[0] + dy * heading[1] < 0.0 {
+            dx = -dx;
+            dy = -dy;
+        }
+
+        // the walls are rounded to the same angles as the roads so this stays on them
+        let angle = dy.atan2(dx);
+        let next_point = [point[0] + angle.cos() * step_length, point[1] + angle.sin() * step_length];
+        if generator.intersect_walls(point, next_point).is_some() {
+            return None;
+        }
+
+        return Some((angle, next_point));
+    }
 }
 
 impl RoadSegment {
+    // a finished road that will not be extended, used when loading levels
+    pub fn from_points(points: Vec<Point>) -> Self {
+        let start_point = points.first().map_or([0.0, 0.0], |point| [point.x, point.y]);
+        let thickness = points.first().map_or(0.0, |point| point.thickness);
+
+        return RoadSegment {
+            start_point,
+            start_angle: 0.0,
+            end_angle: 0.0,
+            thickness,
+            angle: 0.0,
+            points,
+        };
+    }
+
+    // a road already laid along the route that carries on from the end of it when extended
+    pub fn along_route(route: &[[f32; 2]], angle: f32, angle_spread: f32, thickness: f32) -> Self {
+        let (start_point, route) = route.split_last().expect("a route needs at least one point");
+
+        return RoadSegment {
+            start_point: *start_point,
+            start_angle: angle - angle_spread / 2.0,
+            end_angle: angle + angle_spread / 2.0,
+            thickness,
+            angle,
+            points: route.iter().map(|point| Point {
+                x: point[0],
+                y: point[1],
+                thickness,
+            }).collect(),
+        };
+    }
+
     pub fn create(generator: &mut Generator, point: [f32; 2], start_angle: f32, end_angle: f32, thickness: f32) -> Self {
-        let (edge_point, distance) = MathHelper::distance_to_ellipse(0.0, 0.0, SAFE_ZONE_WIDTH / 2.0, SAFE_ZONE_HEIGHT / 2.0, &point);
+        let safe_zone_width = generator.get_config().get_safe_zone_width();
+        let safe_zone_height = generator.get_config().get_safe_zone_height();
+        let (edge_point, _distance) = MathHelper::distance_to_ellipse(0.0, 0.0, safe_zone_width / 2.0, safe_zone_height / 2.0, &point);
 
         let angle_diff = MathHelper::radians_between_angles(start_angle, end_angle);
         let angle = start_angle + angle_diff / 2.0;
@@ -181,19 +376,20 @@ impl RoadSegment {
                 edge_point[1] + angle.sin() * (thickness + TEXTURE_HEIGHT as f32 * 0.0),
             ];
 
-            if MathHelper::is_point_inside_ellipse(point, [0.0, 0.0], [SAFE_ZONE_WIDTH, SAFE_ZONE_HEIGHT]) {
+            if MathHelper::is_point_inside_ellipse(point, [0.0, 0.0], [safe_zone_width, safe_zone_height]) {
                 continue;
             }
 
-            let (is_road1, road_strength) = generator.sample_road(point[0], point[1]);
+            let (is_road1, _road_strength) = generator.sample_road(point[0], point[1]);
             let point2 = [
                 edge_point[0] + angle.cos() * (thickness + TEXTURE_WIDTH as f32 * 2.0),
                 edge_point[1] + angle.sin() * (thickness + TEXTURE_HEIGHT as f32 * 2.0),
             ];
-            let (is_road2, road_strength) = generator.sample_road(point2[0], point2[1]);
+            let (is_road2, _road_strength) = generator.sample_road(point2[0], point2[1]);
             let is_road = is_road1 || is_road2;
 
-            if is_road {
+            // a road with no thickness samples the edge point itself, which has no direction
+            if is_road && thickness > 0.0 {
                 let dx = point[0] - edge_point[0];
                 let dy = point[1] - edge_point[1];
                 let d = (dx * dx + dy * dy).sqrt();
@@ -1,74 +1,38 @@
-use crate::generator::{Generator, SAFE_ZONE_WIDTH, SAFE_ZONE_HEIGHT};
+use crate::generator::{Generator, NoiseLayer};
 use std::f32::consts::PI;
-use crate::math_helper::MathHelper;
-use std::collections::HashSet;
-use rstar::{RTree, AABB, RTreeObject};
-use std::fmt::{Debug, Formatter, Display};
-
-#[derive(Copy, Clone)]
-struct Line {
-    lines: &Vec<[f32; 2]>,
-    index1: usize,
-    index2: usize,
-}
-
-impl Line {
-    fn get_line(&self) -> [[f32; 2]; 2] {
-        return [self.lines[self.index1], self.lines[self.index2]];
-    }
-}
-
-impl Display for Line {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        return write!(f, "{:?} {:?}", self.index1, self.index2);
-    }
-}
-
-impl Debug for Line {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        return write!(f, "{:?} {:?}", self.index1, self.index2);
-    }
-}
-
-impl PartialEq for Line {
-    fn eq(&self, other: &Self) -> bool {
-        return self.index1 == other.index1 && self.index2 == other.index2;
-    }
-}
-
-impl RTreeObject for Line {
-    type Envelope = AABB<[f32; 2]>;
+use crate::math_helper::{MathHelper, Segment};
+use crate::canvas::Canvas;
+use rstar::{RTree, RTreeObject};
 
-    fn envelope(&self) -> Self::Envelope {
-        let min = [self.lines[self.index1][0].min(self.lines[self.index2][0]), self.lines[self.index1][1].min(self.lines[self.index2][1])];
-        let max = [self.lines[self.index1][0].max(self.lines[self.index2][0]), self.lines[self.index1][1].max(self.lines[self.index2][1])];
+// the sine of the angle under which two segments are treated as parallel
+const PARALLEL_EPSILON: f32 = 0.0001;
 
-        return AABB::from_corners(min, max);
-    }
-}
+// radians a segment can be off from the interval and still count as rounded
+const ALIGNED_EPSILON: f32 = 0.0001;
 
+#[derive(Clone)]
 pub struct WallSection {
     pub lines: Vec<[f32; 2]>,
 }
 
 impl WallSection {
-    pub fn render(&mut self, generator: &mut Generator, color: u32) {
-        for index in 0..(self.lines.len() - 1) {
+    pub fn render(&self, canvas: &mut Canvas, color: u32) {
+        for index in 0..self.lines.len().saturating_sub(1) {
             let next_index = index + 1;
 
             let point = self.lines[index];
             let next_point = self.lines[next_index];
 
-            let red = index as f32 / (self.lines.len() - 2) as f32;
+            let red = index as f32 / self.lines.len().saturating_sub(2).max(1) as f32;
             let red = red * 255.0;
             let red = red.round() as u32;
             let color = (color & 0x00ffff) | (red << 16);
 
-            generator.draw_line(point[0], point[1], next_point[0], next_point[1], color, 1.0);
+            canvas.draw_line(point[0], point[1], next_point[0], next_point[1], color, 1.0);
         }
     }
 
-    pub fn connect_points_linear(&mut self, generator: &mut Generator, point1: [f32; 2], point2: [f32; 2]) {
+    pub fn connect_points_linear(&mut self, _generator: &mut Generator, point1: [f32; 2], point2: [f32; 2]) {
         let dx = point2[0] - point1[0];
         let dy = point2[1] - point1[1];
         let distance = (dx * dx + dy * dy).sqrt();
@@ -88,7 +52,7 @@ impl WallSection {
         self.add_point(&point2);
     }
 
-    pub fn connect_points(&mut self, generator: &mut Generator, point1: [f32; 2], tangent1: [f32; 2], point2: [f32; 2], tangent2: [f32; 2]) {
+    pub fn connect_points(&mut self, _generator: &mut Generator, point1: [f32; 2], tangent1: [f32; 2], point2: [f32; 2], tangent2: [f32; 2]) {
         // let d1 = (point1[0] * point1[0] + point1[1] * point1[1]).sqrt();
         // let d2 = (point2[0] * point2[0] + point2[1] * point2[1]).sqrt();
 
@@ -117,107 +81,79 @@ impl WallSection {
         self.add_point(&point2);
     }
 
+    // rewrites the wall so every segment lies on a multiple of interval, keeping every original point
+    // each segment that isn't already aligned is replaced by the two aligned directions around its angle
+    // with a corner inserted where they meet, the leg closest to the original angle comes first
+    // like remove_loops the wall is a closed loop, so the segment from the last point back to the first is rounded too
     pub fn round_to_angle(&mut self, interval: f32) {
-        // start at index 0
-        for i in 0..self.lines.len() {
-            // get the current line of index [i, i + 1], not wrapping back around
-            let mut current_line = [&mut self.lines[i], &mut self.lines[(i + 1) % self.lines.len()]];
-            let current_angle = (current_line[1][1] - current_line[0][1]).atan2(current_line[1][0] - current_line[0][0]);
-            // get the next (infinite) line of index [i + 1, i + 2], wrapping back around if needed
-            let mut next_line = [&mut self.lines[(i + 1) % self.lines.len()], &mut self.lines[(i + 2) % self.lines.len()]];
-            let next_angle = (next_line[1][1] - next_line[0][1]).atan2(next_line[1][0] - next_line[0][0]);
-
-            // round the current line angle up and down to the nearest angles
-            let lower_angle = (current_angle / interval).floor() * interval;
-            let upper_angle = (current_angle / interval).ceil() * interval;
-
-            // find the intersection points of both rounded lines, and the next (infinite) line
-            let lower_intersection = MathHelper::intersect_ray_ray(
-                *current_line[0],
-                [lower_angle.cos(), lower_angle.sin()],
-                *next_line[0],
-                [next_angle.cos(), next_angle.sin()]);
-            let upper_intersection = MathHelper::intersect_ray_ray(
-                *current_line[0],
-                [upper_angle.cos(), upper_angle.sin()],
-                *next_line[0],
-                [next_angle.cos(), next_angle.sin()]);
-
-            if current_line[0][0] == current_line[1][0] && current_line[0][1] == current_line[1][1] {
-                panic!("1 found a segment of length 0 {:?}", self.lines);
-            }
+        self.round_segments(interval, true);
+    }
+
+    // like round_to_angle for an open polyline, its endpoints stay where they are
+    pub fn round_open_to_angle(&mut self, interval: f32) {
+        self.round_segments(interval, false);
+    }
 
-            if next_line[0][0] == next_line[1][0] && next_line[0][1] == next_line[1][1] {
-                panic!("2 found a segment of length 0 {:?}", self.lines);
+    // each segment of the closed wall that round_to_angle puts a corner in, with the corner
+    pub fn get_corners(&self, interval: f32) -> Vec<([[f32; 2]; 2], [f32; 2])> {
+        let count = self.lines.len();
+        let mut corners = Vec::new();
+        for index in 0..count {
+            let segment = [self.lines[index], self.lines[(index + 1) % count]];
+            if let Some(corner) = WallSection::get_corner(segment[0], segment[1], interval) {
+                corners.push((segment, corner));
             }
+        }
 
-            // whichever intersection point is closest to the original vertex [i + 1], [i + 1] becomes that point
-            if let (Some(lower_intersection), Some(upper_intersection)) = (lower_intersection, upper_intersection) {
-                let lower_dx = next_line[0][0] - lower_intersection[0];
-                let lower_dy = next_line[0][1] - lower_intersection[1];
-                let lower_d = lower_dx * lower_dx + lower_dy * lower_dy;
+        return corners;
+    }
 
-                let upper_dx = next_line[0][0] - upper_intersection[0];
-                let upper_dy = next_line[0][1] - upper_intersection[1];
-                let upper_d = upper_dx * upper_dx + upper_dy * upper_dy;
+    fn round_segments(&mut self, interval: f32, closed: bool) {
+        if self.lines.len() < 2 {
+            return;
+        }
 
-                if lower_d < upper_d {
-                    next_line[0][0] = lower_intersection[0];
-                    next_line[0][1] = lower_intersection[1];
-                } else {
-                    next_line[0][0] = upper_intersection[0];
-                    next_line[0][1] = upper_intersection[1];
-                }
-            } else if let Some(lower_intersection) = lower_intersection {
-                next_line[0][0] = lower_intersection[0];
-                next_line[0][1] = lower_intersection[1];
-            } else if let Some(upper_intersection) = upper_intersection {
-                next_line[0][0] = upper_intersection[0];
-                next_line[0][1] = upper_intersection[1];
-            } else {
-                println!("{:?} {:?}", current_line, next_line);
-                panic!("this maybe shouldnt happen");
-                let lower_angle = lower_angle - interval;
-                let upper_angle = upper_angle - interval;
-
-                let lower_intersection = MathHelper::intersect_ray_ray(
-                    *current_line[0],
-                    [lower_angle.cos(), lower_angle.sin()],
-                    *next_line[0],
-                    [next_angle.cos(), next_angle.sin()]);
-                let upper_intersection = MathHelper::intersect_ray_ray(
-                    *current_line[0],
-                    [upper_angle.cos(), upper_angle.sin()],
-                    *next_line[0],
-                    [next_angle.cos(), next_angle.sin()]);
-
-                if let (Some(lower_intersection), Some(upper_intersection)) = (lower_intersection, upper_intersection) {
-                    let lower_dx = next_line[0][0] - lower_intersection[0];
-                    let lower_dy = next_line[0][1] - lower_intersection[1];
-                    let lower_d = lower_dx * lower_dx + lower_dy * lower_dy;
-
-                    let upper_dx = next_line[0][0] - upper_intersection[0];
-                    let upper_dy = next_line[0][1] - upper_intersection[1];
-                    let upper_d = upper_dx * upper_dx + upper_dy * upper_dy;
-
-                    if lower_d < upper_d {
-                        next_line[0][0] = lower_intersection[0];
-                        next_line[0][1] = lower_intersection[1];
-                    } else {
-                        next_line[0][0] = upper_intersection[0];
-                        next_line[0][1] = upper_intersection[1];
-                    }
-                } else if let Some(lower_intersection) = lower_intersection {
-                    next_line[0][0] = lower_intersection[0];
-                    next_line[0][1] = lower_intersection[1];
-                } else if let Some(upper_intersection) = upper_intersection {
-                    next_line[0][0] = upper_intersection[0];
-                    next_line[0][1] = upper_intersection[1];
-                } else {
-                    panic!("This should actually mathematically never happen.");
-                }
+        let count = self.lines.len();
+        let segments = if closed {count} else {count - 1};
+        let mut lines = Vec::with_capacity(count * 2);
+        lines.push(self.lines[0]);
+
+        for index in 0..segments {
+            let point = self.lines[index];
+            let next_point = self.lines[(index + 1) % count];
+            if let Some(corner) = WallSection::get_corner(point, next_point, interval) {
+                lines.push(corner);
             }
+
+            // the first point is already at the start
+            if index + 1 < count {
+                lines.push(next_point);
+            }
+        }
+
+        self.lines = lines;
+    }
+
+    // where rounding puts the corner between two points, none if the segment is already aligned
+    fn get_corner(point: [f32; 2], next_point: [f32; 2], interval: f32) -> Option<[f32; 2]> {
+        let dx = next_point[0] - point[0];
+        let dy = next_point[1] - point[1];
+        if dx == 0.0 && dy == 0.0 {
+            return None;
+        }
+
+        let angle = dy.atan2(dx);
+        let near_angle = MathHelper::round_to_interval(angle, interval);
+        if (angle - near_angle).abs() <= ALIGNED_EPSILON {
+            return None;
         }
+
+        let far_angle = if angle > near_angle {near_angle + interval} else {near_angle - interval};
+        return MathHelper::intersect_ray_ray(
+            point,
+            [near_angle.cos(), near_angle.sin()],
+            next_point,
+            [far_angle.cos(), far_angle.sin()]);
     }
 
     pub fn noiseify(&mut self, generator: &mut Generator, strength: f32, scale: f32, center: [f32; 2], offset_angle: f32) {
@@ -228,7 +164,7 @@ impl WallSection {
 
             let point = &mut self.lines[i];
 
-            let perlin = generator.get_perlin_value(point[0] + 56342.0, point[1] + 90678.0, scale);
+            let perlin = generator.get_noise_value(NoiseLayer::WallWobble, point[0], point[1], scale);
             let angle = (point[1] - center[1]).atan2(point[0] - center[0]);
             let angle = angle + offset_angle;
             let dx = angle.cos();
@@ -237,6 +173,9 @@ impl WallSection {
             point[0] += dx * perlin * strength * ease;
             point[1] += dy * perlin * strength * ease;
         }
+
+        // strong noise can fold the wall back over itself
+        self.remove_open_loops();
     }
 
     pub fn join_wall(&mut self, mut wall: WallSection) {
@@ -377,7 +316,7 @@ impl WallSection {
     }
 
     pub fn add_point(&mut self, point: &[f32; 2]) {
-        self.lines.push(point.clone());
+        self.lines.push(*point);
     }
 
     pub fn distance_to_wall(&self, point: &[f32; 2]) -> ([f32; 2], f32) {
@@ -430,11 +369,11 @@ impl WallSection {
                 normal = angle + PI / 2.0;
             }
 
-            let perlin1 = (generator.get_perlin_value(point[0] + 3452.0, point[1] + 3452.0, 10.0) + 1.0) / 2.0;
-            let perlin2 = (generator.get_perlin_value(point[0] + 87362.0, point[1] + 87362.0, 10.0) + 1.0) / 2.0;
+            let perlin1 = (generator.get_noise_value(NoiseLayer::WallThickness, point[0], point[1], 10.0) + 1.0) / 2.0;
+            let perlin2 = (generator.get_noise_value(NoiseLayer::WallThicknessSecondary, point[0], point[1], 10.0) + 1.0) / 2.0;
             let perlin = perlin1 * perlin2;
             let thickness_mod = perlin * 8.0 - 0.5;
-            // let thickness_mod = thickness_mod + generator.get_perlin_value(point[0], point[1], 0.1) * 400.0;
+            // let thickness_mod = thickness_mod + generator.get_noise_value(NoiseLayer::WallThickness, point[0], point[1], 0.1) * 400.0;
             let thickness = (index as f32 / (self.lines.len() - 1) as f32) * (end_thickness - start_thickness) + start_thickness;
             let thickness = (thickness * thickness_mod).max(200.0);
             wall_1.add_point(&[point[0] + normal.cos() * thickness, point[1] + normal.sin() * thickness]);
@@ -445,9 +384,11 @@ impl WallSection {
     }
 
     pub fn fill_wall(&mut self, generator: &mut Generator, length: f32, angle: f32, desired_angle: f32, desired_angle_strength: f32, distance_wall: Option<&WallSection>) {
+        let safe_zone_width = generator.get_config().get_safe_zone_width();
+        let safe_zone_height = generator.get_config().get_safe_zone_height();
         let mut point = [
-            angle.cos() * SAFE_ZONE_WIDTH / 2.0,
-            angle.sin() * SAFE_ZONE_HEIGHT / 2.0,
+            angle.cos() * safe_zone_width / 2.0,
+            angle.sin() * safe_zone_height / 2.0,
         ];
 
         let inner_offset_length = generator.next() * 400.0;
@@ -461,7 +402,7 @@ impl WallSection {
 
         self.add_point(&point);
         while current_wall_length < length {
-            let angle_mod = generator.get_perlin_value(point[0] - 10240.0, point[1] - 10240.0, 1.0);
+            let angle_mod = generator.get_noise_value(NoiseLayer::WallDrift, point[0], point[1], 1.0);
             current_angle += angle_mod * PI * 0.1;
 
             let desired_difference = MathHelper::radians_between_angles(current_angle, desired_angle);
@@ -500,63 +441,166 @@ impl WallSection {
         }
     }
 
+    // treats the wall as a closed loop, where the last point connects back to the first
+    // every self intersection splits the loop in two and the one enclosing the larger area is kept
     pub fn remove_loops(&mut self) {
-        // create the lookupable tree of lines
-        let mut rtree: RTree<Line> = RTree::new();
-
-        let mut new_lines: Vec<Line> = Vec::new();
+        self.remove_intersections(true);
+    }
 
-        // I decided to go with a sub optimal solution that can potentially destroy entire polygons
-        // if the intersection happens the wrong way, like by extending through an existing line
-        // instead of being a loop
+    // treats the wall as an open polyline with fixed endpoints, every loop it makes is cut out
+    pub fn remove_open_loops(&mut self) {
+        self.remove_intersections(false);
+    }
 
+    // the area enclosed by the wall as if it were closed
+    pub fn get_area(&self) -> f32 {
+        let mut area = 0.0;
         for index in 0..self.lines.len() {
-            let line = Line {
-                lines: &self.lines,
-                index1: index,
-                index2: (index + 1) % self.lines.len(),
-            };
-            let line_line = line.get_line();
-            let line_aabb = line.envelope();
-
-            let collisions = rtree.locate_in_envelope_intersecting(&line_aabb);
-            for existing in collisions.into_iter() {
-                // if the line we're testing is the previous line skip it
-
-                if existing.index2 == line.index1 || existing.index1 == line.index2 {
+            let point = self.lines[index];
+            let next_point = self.lines[(index + 1) % self.lines.len()];
+            area += point[0] * next_point[1] - next_point[0] * point[1];
+        }
+
+        return (area / 2.0).abs();
+    }
+
+    fn remove_intersections(&mut self, closed: bool) {
+        self.remove_degenerate_points(closed);
+
+        // every cut removes at least one segment so this always finishes
+        while let Some((first, second, intersection)) = self.find_intersection(closed) {
+            let before: Vec<[f32; 2]> = self.lines[..=first].iter().copied()
+                .chain(std::iter::once(intersection))
+                .chain(self.lines[(second + 1)..].iter().copied())
+                .collect();
+
+            if closed {
+                let loop_section = WallSection {
+                    lines: std::iter::once(intersection).chain(self.lines[(first + 1)..=second].iter().copied()).collect(),
+                };
+                let remaining_section = WallSection {
+                    lines: before,
+                };
+
+                if loop_section.get_area() > remaining_section.get_area() {
+                    self.lines = loop_section.lines;
+                } else {
+                    self.lines = remaining_section.lines;
+                }
+            } else {
+                self.lines = before;
+            }
+
+            self.remove_degenerate_points(closed);
+        }
+    }
+
+    // finds the first segment that crosses or touches a later segment it isn't connected to
+    // and the last segment it hits, so a single cut removes the whole loop
+    fn find_intersection(&self, closed: bool) -> Option<(usize, usize, [f32; 2])> {
+        let count = self.lines.len();
+        let segment_count = if closed {count} else {count.saturating_sub(1)};
+        if segment_count < 3 {
+            return None;
+        }
+
+        // each segment knows the index of its first point
+        let segments: Vec<Segment<usize>> = (0..segment_count).map(|index| {
+            return Segment::create(self.lines[index], self.lines[(index + 1) % count], index);
+        }).collect();
+        let rtree = RTree::bulk_load(segments.clone());
+
+        for segment in &segments {
+            let mut found: Option<(usize, [f32; 2])> = None;
+            for other in rtree.locate_in_envelope_intersecting(&segment.envelope()) {
+                // only look forward, and skip the neighbours that share a vertex with this segment
+                if other.data <= segment.data + 1 {
+                    continue;
+                }
+                if closed && segment.data == 0 && other.data == segment_count - 1 {
+                    continue;
+                }
+                if found.is_some_and(|(index, _point)| index >= other.data) {
                     continue;
                 }
 
-                let existing_line = existing.get_line();
-                if let Some(intersection) = MathHelper::intersect_line_line(line_line, existing_line) {
-                    println!("found collision {:?} {:?}", existing, line);
-                    self.lines[line.index1][0] = intersection[0];
-                    self.lines[line.index1][1] = intersection[1];
-
-                    // delete from [existing_line.index2, line_line.index1)
-                    // so basically keep popping off the last entry until you get to the entry where index2 is existing_line.index2
-                    // then change existing_line.index2 to line_line.index1
-                    println!("removing from {:?} to {:?}", new_lines[new_lines.len() - 1].index2, existing.index2);
-                    while new_lines[new_lines.len() - 1].index2 > existing.index2 {
-                        let removed_line = new_lines.pop();
-
-                        if let Some(removed_line) = removed_line {
-                            rtree.remove(&removed_line);
-                        }
-                    }
-                    new_lines[new_lines.len() - 1].index2 = line.index1;
-
-                    break;
+                if let Some(intersection) = WallSection::intersect_segments(segment.points, other.points) {
+                    found = Some((other.data, intersection));
                 }
             }
 
-            new_lines.push(line);
-            rtree.insert(line);
+            if let Some((other_index, intersection)) = found {
+                return Some((segment.data, other_index, intersection));
+            }
         }
 
-        self.lines = new_lines.iter().map(|line| {
-            return [self.lines[line.index1][0], self.lines[line.index1][1]];
-        }).collect();
+        return None;
+    }
+
+    // like MathHelper::intersect_line_line, but overlapping collinear segments also count
+    // in which case the overlapping point closest to the start of the first segment is used
+    fn intersect_segments(line1: [[f32; 2]; 2], line2: [[f32; 2]; 2]) -> Option<[f32; 2]> {
+        let direction = [line1[1][0] - line1[0][0], line1[1][1] - line1[0][1]];
+        let other_direction = [line2[1][0] - line2[0][0], line2[1][1] - line2[0][1]];
+        let length2 = direction[0] * direction[0] + direction[1] * direction[1];
+        let other_length2 = other_direction[0] * other_direction[0] + other_direction[1] * other_direction[1];
+
+        let cross = direction[0] * other_direction[1] - direction[1] * other_direction[0];
+        if cross.abs() > PARALLEL_EPSILON * (length2 * other_length2).sqrt() {
+            return MathHelper::intersect_line_line(line1, line2);
+        }
+
+        // parallel, so they only meet if line2 sits on the same infinite line as line1
+        let offset = [line2[0][0] - line1[0][0], line2[0][1] - line1[0][1]];
+        let offset_cross = direction[0] * offset[1] - direction[1] * offset[0];
+        if length2 == 0.0 || offset_cross.abs() > PARALLEL_EPSILON * (length2 * (offset[0] * offset[0] + offset[1] * offset[1])).sqrt() {
+            return None;
+        }
+
+        // project line2 onto line1 and clip it to line1
+        let project = |point: [f32; 2]| ((point[0] - line1[0][0]) * direction[0] + (point[1] - line1[0][1]) * direction[1]) / length2;
+        let start = project(line2[0]);
+        let end = project(line2[1]);
+        let first = start.min(end).max(0.0);
+        let last = start.max(end).min(1.0);
+        if first > last {
+            return None;
+        }
+
+        return Some([line1[0][0] + direction[0] * first, line1[0][1] + direction[1] * first]);
+    }
+
+    // removes repeated points and spikes where the wall doubles straight back over itself
+    fn remove_degenerate_points(&mut self, closed: bool) {
+        let mut index = 0;
+        while self.lines.len() > 2 && index < self.lines.len() {
+            let count = self.lines.len();
+            if !closed && (index == 0 || index == count - 1) {
+                index += 1;
+                continue;
+            }
+
+            let previous = self.lines[(index + count - 1) % count];
+            let point = self.lines[index];
+            let next = self.lines[(index + 1) % count];
+
+            let incoming = [point[0] - previous[0], point[1] - previous[1]];
+            let outgoing = [next[0] - point[0], next[1] - point[1]];
+            let cross = incoming[0] * outgoing[1] - incoming[1] * outgoing[0];
+            let dot = incoming[0] * outgoing[0] + incoming[1] * outgoing[1];
+            let incoming_length2 = incoming[0] * incoming[0] + incoming[1] * incoming[1];
+            let outgoing_length2 = outgoing[0] * outgoing[0] + outgoing[1] * outgoing[1];
+
+            let repeated = incoming_length2 == 0.0 || outgoing_length2 == 0.0;
+            let spike = dot < 0.0 && cross.abs() <= PARALLEL_EPSILON * (incoming_length2 * outgoing_length2).sqrt();
+            if repeated || spike {
+                self.lines.remove(index);
+                // the previous point might be a spike now
+                index = index.saturating_sub(1);
+            } else {
+                index += 1;
+            }
+        }
     }
 }
 
@@ -566,4 +610,135 @@ impl Default for WallSection {
             lines: Vec::new(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wall(points: &[[f32; 2]]) -> WallSection {
+        return WallSection {
+            lines: points.to_vec(),
+        };
+    }
+
+    #[test]
+    fn closed_loop_keeps_the_larger_side() {
+        // a big square with a small bow tie loop crossing itself at (300, 100)
+        let mut section = wall(&[
+            [0.0, 0.0], [200.0, 0.0], [400.0, 200.0], [400.0, 0.0], [200.0, 200.0],
+            [200.0, 1000.0], [0.0, 1000.0],
+        ]);
+        section.remove_loops();
+
+        assert_eq!(section.lines, vec![[0.0, 0.0], [200.0, 0.0], [300.0, 100.0], [200.0, 200.0], [200.0, 1000.0], [0.0, 1000.0]]);
+        assert!(section.find_intersection(true).is_none());
+    }
+
+    #[test]
+    fn closed_loop_keeps_the_loop_when_it_is_larger() {
+        // the small part is the one outside the crossing at (100, 0)
+        let mut section = wall(&[
+            [0.0, -50.0], [100.0, 0.0], [1000.0, 0.0], [1000.0, 1000.0], [100.0, 1000.0], [100.0, -50.0], [200.0, 0.0],
+        ]);
+        section.remove_loops();
+
+        assert!(section.get_area() > 800000.0);
+        assert!(section.find_intersection(true).is_none());
+    }
+
+    #[test]
+    fn open_polyline_has_its_loop_cut_out() {
+        let mut section = wall(&[[0.0, 0.0], [200.0, 0.0], [200.0, 100.0], [100.0, 100.0], [100.0, -100.0], [100.0, -200.0]]);
+        section.remove_open_loops();
+
+        assert_eq!(section.lines, vec![[0.0, 0.0], [100.0, 0.0], [100.0, -100.0], [100.0, -200.0]]);
+    }
+
+    #[test]
+    fn open_polyline_endpoints_are_kept() {
+        let mut section = wall(&[[0.0, 0.0], [100.0, 0.0], [100.0, 100.0], [200.0, 100.0]]);
+        section.remove_open_loops();
+
+        assert_eq!(section.lines, vec![[0.0, 0.0], [100.0, 0.0], [100.0, 100.0], [200.0, 100.0]]);
+    }
+
+    #[test]
+    fn collinear_overlapping_segments() {
+        let hit = WallSection::intersect_segments([[0.0, 0.0], [100.0, 0.0]], [[150.0, 0.0], [50.0, 0.0]]);
+        assert_eq!(hit, Some([50.0, 0.0]));
+
+        let hit = WallSection::intersect_segments([[0.0, 0.0], [100.0, 0.0]], [[-50.0, 0.0], [20.0, 0.0]]);
+        assert_eq!(hit, Some([0.0, 0.0]));
+
+        // parallel but on different lines, and collinear but apart
+        assert!(WallSection::intersect_segments([[0.0, 0.0], [100.0, 0.0]], [[0.0, 10.0], [100.0, 10.0]]).is_none());
+        assert!(WallSection::intersect_segments([[0.0, 0.0], [100.0, 0.0]], [[150.0, 0.0], [200.0, 0.0]]).is_none());
+
+        // an open wall that runs back over itself is cut where the overlap starts
+        let mut section = wall(&[[0.0, 0.0], [100.0, 0.0], [100.0, 50.0], [50.0, 50.0], [50.0, 0.0], [20.0, 0.0], [20.0, -100.0]]);
+        section.remove_open_loops();
+        assert!(section.find_intersection(false).is_none());
+        assert_eq!(section.lines.first(), Some(&[0.0, 0.0]));
+        assert_eq!(section.lines.last(), Some(&[20.0, -100.0]));
+    }
+
+    #[test]
+    fn segments_touching_at_a_vertex() {
+        let hit = WallSection::intersect_segments([[0.0, 0.0], [100.0, 0.0]], [[100.0, 0.0], [100.0, 100.0]]);
+        assert!(hit.is_some_and(|hit| (hit[0] - 100.0).abs() < 0.01 && hit[1].abs() < 0.01));
+
+        // a later vertex landing on an earlier one closes a loop, which is cut at that vertex
+        let mut section = wall(&[[0.0, 0.0], [100.0, 0.0], [200.0, 0.0], [200.0, 100.0], [100.0, 0.0], [100.0, -100.0]]);
+        section.remove_open_loops();
+
+        assert_eq!(section.lines, vec![[0.0, 0.0], [100.0, 0.0], [100.0, -100.0]]);
+    }
+
+    fn is_aligned(from: [f32; 2], to: [f32; 2], interval: f32) -> bool {
+        let angle = (to[1] - from[1]).atan2(to[0] - from[0]);
+        return (angle - MathHelper::round_to_interval(angle, interval)).abs() < 0.001;
+    }
+
+    #[test]
+    fn round_to_angle_keeps_points_and_aligns_every_segment() {
+        let points = [[0.0, 0.0], [1000.0, 300.0], [1200.0, 1100.0], [-100.0, 900.0]];
+        let mut section = wall(&points);
+        section.round_to_angle(PI / 4.0);
+
+        for point in &points {
+            assert!(section.lines.contains(point));
+        }
+
+        // the closing segment back to the first point is rounded like the rest
+        let count = section.lines.len();
+        for index in 0..count {
+            assert!(is_aligned(section.lines[index], section.lines[(index + 1) % count], PI / 4.0), "segment {} of {:?}", index, section.lines);
+        }
+        assert_eq!(count, points.len() * 2);
+    }
+
+    #[test]
+    fn round_open_to_angle_keeps_its_endpoints() {
+        let points = [[0.0, 0.0], [1000.0, 300.0], [1200.0, 1100.0], [-100.0, 900.0]];
+        let mut section = wall(&points);
+        section.round_open_to_angle(PI / 4.0);
+
+        // nothing is added after the last point or between it and the first
+        assert_eq!(section.get_first_point(), points[0]);
+        assert_eq!(section.get_last_point(), points[3]);
+        assert_eq!(section.lines.len(), points.len() * 2 - 1);
+        for pair in section.lines.windows(2) {
+            assert!(is_aligned(pair[0], pair[1], PI / 4.0), "{:?} in {:?}", pair, section.lines);
+        }
+    }
+
+    #[test]
+    fn round_to_angle_leaves_aligned_walls_alone() {
+        let points = [[0.0, 0.0], [100.0, 0.0], [200.0, 100.0], [200.0, 300.0], [0.0, 300.0]];
+        let mut section = wall(&points);
+        section.round_to_angle(PI / 4.0);
+
+        assert_eq!(section.lines, points.to_vec());
+    }
+}
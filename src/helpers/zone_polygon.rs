@@ -0,0 +1,209 @@
+use crate::helpers::wall_section::WallSection;
+use crate::math_helper::MathHelper;
+use crate::level::SafeZone;
+use std::f32::consts::PI;
+use std::collections::HashMap;
+
+const ELLIPSE_SEGMENTS: usize = 64;
+
+// a closed polygon, the last point connects back to the first
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZonePolygon {
+    pub points: Vec<[f32; 2]>,
+}
+
+impl ZonePolygon {
+    pub fn from_ellipse(center: [f32; 2], width: f32, height: f32) -> Self {
+        let points = (0..ELLIPSE_SEGMENTS).map(|index| {
+            let angle = index as f32 / ELLIPSE_SEGMENTS as f32 * PI * 2.0;
+            return [center[0] + angle.cos() * width / 2.0, center[1] + angle.sin() * height / 2.0];
+        }).collect();
+
+        return ZonePolygon {
+            points,
+        };
+    }
+
+    // walks the walls in order, each flagged if it should be walked backwards
+    // walls that don't share an endpoint are connected with a straight line
+    // the ends are then closed along the edge of the safe zone so the polygon stops where the safe zone begins
+    // corners are the ones the walls got from WallSection::get_corners when they were rounded, and go in
+    // wherever the polygon runs along the segment they were made for, they depend on which way the wall ran
+    // so the polygon can't be rounded itself
+    pub fn from_walls(walls: &[(&WallSection, bool)], corners: &[([[f32; 2]; 2], [f32; 2])], safe_zone: &SafeZone) -> Self {
+        let mut polygon = ZonePolygon::default();
+        for (wall, reversed) in walls {
+            if *reversed {
+                for point in wall.lines.iter().rev() {
+                    polygon.add_point(point);
+                }
+            } else {
+                for point in &wall.lines {
+                    polygon.add_point(point);
+                }
+            }
+        }
+
+        polygon.close_along_ellipse(safe_zone.center, safe_zone.width, safe_zone.height);
+        polygon.add_corners(corners);
+
+        let mut outline = WallSection {
+            lines: polygon.points,
+        };
+        outline.remove_loops();
+
+        return ZonePolygon {
+            points: outline.lines,
+        };
+    }
+
+    pub fn add_point(&mut self, point: &[f32; 2]) {
+        if self.points.last() == Some(point) {
+            return;
+        }
+
+        self.points.push(*point);
+    }
+
+    pub fn get_area(&self) -> f32 {
+        let outline = WallSection {
+            lines: self.points.clone(),
+        };
+
+        return outline.get_area();
+    }
+
+    // even odd rule, points exactly on an edge can land on either side
+    pub fn contains(&self, point: &[f32; 2]) -> bool {
+        let mut inside = false;
+        let mut previous = self.points.len().wrapping_sub(1);
+        for index in 0..self.points.len() {
+            let a = self.points[index];
+            let b = self.points[previous];
+            if (a[1] > point[1]) != (b[1] > point[1]) {
+                let x = a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]);
+                if point[0] < x {
+                    inside = !inside;
+                }
+            }
+
+            previous = index;
+        }
+
+        return inside;
+    }
+
+    fn add_corners(&mut self, corners: &[([[f32; 2]; 2], [f32; 2])]) {
+        let key = |first: [f32; 2], second: [f32; 2]| [first[0].to_bits(), first[1].to_bits(), second[0].to_bits(), second[1].to_bits()];
+
+        let mut corner_map = HashMap::new();
+        for (segment, corner) in corners {
+            corner_map.insert(key(segment[0], segment[1]), *corner);
+            corner_map.insert(key(segment[1], segment[0]), *corner);
+        }
+
+        let count = self.points.len();
+        let mut points = Vec::with_capacity(count);
+        for index in 0..count {
+            let point = self.points[index];
+            points.push(point);
+            if let Some(corner) = corner_map.get(&key(point, self.points[(index + 1) % count])) {
+                points.push(*corner);
+            }
+        }
+
+        self.points = points;
+    }
+
+    // adds an arc from the last point back around to the first point along the ellipse
+    // the arc winds opposite to the rest of the polygon so the ellipse center is left outside
+    fn close_along_ellipse(&mut self, center: [f32; 2], width: f32, height: f32) {
+        if self.points.len() < 2 {
+            return;
+        }
+
+        let angle_of = |point: &[f32; 2]| (point[1] - center[1]).atan2(point[0] - center[0]);
+
+        let mut sweep = 0.0;
+        for index in 0..(self.points.len() - 1) {
+            sweep += MathHelper::radians_between_angles(angle_of(&self.points[index]), angle_of(&self.points[index + 1]));
+        }
+
+        let start_angle = angle_of(&self.points[self.points.len() - 1]);
+        let steps = ((sweep.abs() / (PI * 2.0) * ELLIPSE_SEGMENTS as f32).ceil() as usize).max(1);
+        for step in 0..=steps {
+            let angle = start_angle - sweep * step as f32 / steps as f32;
+            self.add_point(&[center[0] + angle.cos() * width / 2.0, center[1] + angle.sin() * height / 2.0]);
+        }
+    }
+}
+
+impl Default for ZonePolygon {
+    fn default() -> Self {
+        return ZonePolygon {
+            points: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ellipse_area_and_contains() {
+        let polygon = ZonePolygon::from_ellipse([100.0, 50.0], 400.0, 200.0);
+
+        let area = PI * 200.0 * 100.0;
+        assert!((polygon.get_area().abs() - area).abs() < area * 0.01);
+        assert!(polygon.contains(&[100.0, 50.0]));
+        assert!(polygon.contains(&[290.0, 50.0]));
+        assert!(!polygon.contains(&[310.0, 50.0]));
+        assert!(!polygon.contains(&[100.0, 160.0]));
+    }
+
+    #[test]
+    fn add_point_skips_repeats() {
+        let mut polygon = ZonePolygon::default();
+        polygon.add_point(&[0.0, 0.0]);
+        polygon.add_point(&[0.0, 0.0]);
+        polygon.add_point(&[1.0, 0.0]);
+
+        assert_eq!(polygon.points, vec![[0.0, 0.0], [1.0, 0.0]]);
+    }
+
+    #[test]
+    fn corners_are_added_whichever_way_the_wall_is_walked() {
+        let wall = WallSection {
+            lines: vec![[1000.0, 0.0], [2000.0, 300.0], [1000.0, 1000.0]],
+        };
+        let corners = wall.get_corners(PI / 4.0);
+        let safe_zone = SafeZone {
+            center: [0.0, 0.0],
+            width: 200.0,
+            height: 200.0,
+        };
+
+        // the segment closing the wall is already aligned
+        assert_eq!(corners.len(), 2);
+        for reversed in [false, true] {
+            let polygon = ZonePolygon::from_walls(&[(&wall, reversed)], &corners, &safe_zone);
+            for (_segment, corner) in &corners {
+                assert!(polygon.points.contains(corner), "{:?} missing from {:?}", corner, polygon.points);
+            }
+        }
+    }
+
+    #[test]
+    fn tiers_stop_at_the_safe_zone() {
+        let level = crate::generate(3);
+        let center = level.safe_zone.center;
+
+        assert!(level.zones.safe_zone.contains(&center));
+        for zone in [&level.zones.t1, &level.zones.t2, &level.zones.t3] {
+            assert!(zone.points.len() > 3);
+            assert!(zone.get_area().abs() > 0.0);
+            assert!(!zone.contains(&center));
+        }
+    }
+}
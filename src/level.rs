@@ -0,0 +1,312 @@
+use crate::helpers::wall_section::WallSection;
+use crate::helpers::road_segment::RoadSegment;
+use crate::helpers::zone_polygon::ZonePolygon;
+use crate::canvas::Canvas;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+use crate::export::{json, svg, tiled};
+use crate::level_index::LevelIndex;
+use crate::road_network::RoadNetwork;
+use std::sync::OnceLock;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tile {
+    Grass,
+    Gravel,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SafeZone {
+    pub center: [f32; 2],
+    pub width: f32,
+    pub height: f32,
+}
+
+// an opening cut through a divider wall by split_for_path
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WallPath {
+    pub center: [f32; 2],
+    // unit vector through the opening, from the first face of the wall to the second
+    pub direction: [f32; 2],
+    // narrowest distance across the opening
+    pub width: f32,
+    // how far it is through the opening from one face of the wall to the other
+    pub depth: f32,
+}
+
+impl WallPath {
+    // each side is the two corners of the opening on one face of the wall
+    pub fn between(side_1: [[f32; 2]; 2], side_2: [[f32; 2]; 2]) -> Self {
+        let middle_1 = [(side_1[0][0] + side_1[1][0]) / 2.0, (side_1[0][1] + side_1[1][1]) / 2.0];
+        let middle_2 = [(side_2[0][0] + side_2[1][0]) / 2.0, (side_2[0][1] + side_2[1][1]) / 2.0];
+
+        let dx = middle_2[0] - middle_1[0];
+        let dy = middle_2[1] - middle_1[1];
+        let depth = (dx * dx + dy * dy).sqrt();
+
+        let width_1 = ((side_1[1][0] - side_1[0][0]).powi(2) + (side_1[1][1] - side_1[0][1]).powi(2)).sqrt();
+        let width_2 = ((side_2[1][0] - side_2[0][0]).powi(2) + (side_2[1][1] - side_2[0][1]).powi(2)).sqrt();
+
+        return WallPath {
+            center: [(middle_1[0] + middle_2[0]) / 2.0, (middle_1[1] + middle_2[1]) / 2.0],
+            direction: if depth > 0.0 {[dx / depth, dy / depth]} else {[0.0, 0.0]},
+            width: width_1.min(width_2),
+            depth,
+        };
+    }
+
+    // the point just outside the opening on the far side for something heading in the given direction
+    pub fn get_exit(&self, heading: [f32; 2], clearance: f32) -> [f32; 2] {
+        let sign = if heading[0] * self.direction[0] + heading[1] * self.direction[1] < 0.0 {-1.0} else {1.0};
+        let offset = self.depth / 2.0 + clearance;
+
+        return [
+            self.center[0] + self.direction[0] * sign * offset,
+            self.center[1] + self.direction[1] * sign * offset,
+        ];
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Zone {
+    Safe,
+    T1,
+    T2,
+    T3,
+    Outside,
+}
+
+// every wall the generator produces, the back walls are kept separately even though they're also joined into the outer wall
+#[derive(Clone)]
+pub struct LevelWalls {
+    pub t1_inner_1: WallSection,
+    pub t1_inner_2: WallSection,
+    pub t2_t1_inner: WallSection,
+    pub t3_t2_inner: WallSection,
+    pub outer_wall: WallSection,
+    pub t1_upper_back_wall: WallSection,
+    pub t1_lower_back_wall: WallSection,
+    pub t2_back_wall: WallSection,
+    pub t3_back_wall: WallSection,
+}
+
+impl LevelWalls {
+    // the closed walls that make up the final level
+    pub fn boundaries(&self) -> Vec<(&'static str, &WallSection)> {
+        return vec![
+            ("t1_inner_1", &self.t1_inner_1),
+            ("t1_inner_2", &self.t1_inner_2),
+            ("t2_t1_inner", &self.t2_t1_inner),
+            ("t3_t2_inner", &self.t3_t2_inner),
+            ("outer_wall", &self.outer_wall),
+        ];
+    }
+
+    pub fn back_walls(&self) -> Vec<(&'static str, &WallSection)> {
+        return vec![
+            ("t1_upper_back_wall", &self.t1_upper_back_wall),
+            ("t1_lower_back_wall", &self.t1_lower_back_wall),
+            ("t2_back_wall", &self.t2_back_wall),
+            ("t3_back_wall", &self.t3_back_wall),
+        ];
+    }
+
+    pub fn iter(&self) -> Vec<(&'static str, &WallSection)> {
+        let mut walls = self.boundaries();
+        walls.append(&mut self.back_walls());
+
+        return walls;
+    }
+}
+
+// the tier polygons are bounded by the divider walls facing them and their back walls, rounded the same way, and stop at the safe zone
+// the inner walls sit inside their tier, and a divider's gap is crossed by the straight line its wall would have taken
+#[derive(Clone)]
+pub struct LevelZones {
+    pub safe_zone: ZonePolygon,
+    pub t1: ZonePolygon,
+    pub t2: ZonePolygon,
+    pub t3: ZonePolygon,
+}
+
+impl LevelZones {
+    pub fn iter(&self) -> Vec<(Zone, &ZonePolygon)> {
+        return vec![
+            (Zone::Safe, &self.safe_zone),
+            (Zone::T1, &self.t1),
+            (Zone::T2, &self.t2),
+            (Zone::T3, &self.t3),
+        ];
+    }
+}
+
+pub struct Level {
+    pub seed: u64,
+    // the tile grid is centered on the origin, so tile (0, 0) is at index (height / 2) * width + width / 2
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<Tile>,
+    pub safe_zone: SafeZone,
+    pub walls: LevelWalls,
+    pub zones: LevelZones,
+    pub roads: Vec<RoadSegment>,
+    // built by the first spatial query, so the walls and zones shouldn't be changed after querying
+    pub(crate) index: OnceLock<LevelIndex>,
+    // built the first time it's asked for, same as the index the roads shouldn't change after that
+    pub(crate) road_network: OnceLock<RoadNetwork>,
+}
+
+#[derive(Copy, Clone, Debug, Serialize)]
+pub struct LevelStats {
+    pub seed: u64,
+    pub wall_count: usize,
+    pub wall_points: usize,
+    pub wall_length: f32,
+    pub road_count: usize,
+    pub road_points: usize,
+    pub road_length: f32,
+    pub gravel_tiles: usize,
+}
+
+impl Level {
+    pub fn get_stats(&self) -> LevelStats {
+        let walls = self.walls.boundaries();
+
+        return LevelStats {
+            seed: self.seed,
+            wall_count: walls.len(),
+            wall_points: walls.iter().map(|(_name, wall)| wall.lines.len()).sum(),
+            wall_length: walls.iter().map(|(_name, wall)| wall.get_length()).sum(),
+            road_count: self.roads.len(),
+            road_points: self.roads.iter().map(|road| road.points.len()).sum(),
+            road_length: self.roads.iter().map(|road| road.get_length()).sum(),
+            gravel_tiles: self.tiles.iter().filter(|tile| **tile == Tile::Gravel).count(),
+        };
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        return json::to_json(self);
+    }
+
+    pub fn from_json(source: &str) -> Result<Level, serde_json::Error> {
+        return json::from_json(source);
+    }
+
+    pub fn zone_at(&self, x: f32, y: f32) -> Zone {
+        return self.get_index().zone_at([x, y]);
+    }
+
+    // nearest point on any of the boundary walls and the distance to it
+    pub fn distance_to_wall(&self, x: f32, y: f32) -> ([f32; 2], f32) {
+        return self.get_index().distance_to_wall([x, y]);
+    }
+
+    pub fn get_road_network(&self) -> &RoadNetwork {
+        return self.road_network.get_or_init(|| RoadNetwork::create(&self.roads, &self.safe_zone));
+    }
+
+    fn get_index(&self) -> &LevelIndex {
+        return self.index.get_or_init(|| LevelIndex::create(&self.walls, &self.zones));
+    }
+
+    pub fn get_tile(&self, x: i32, y: i32) -> Option<Tile> {
+        let x = x + self.width as i32 / 2;
+        let y = y + self.height as i32 / 2;
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+
+        return Some(self.tiles[y as usize * self.width + x as usize]);
+    }
+
+    pub fn render_pixels(&self) -> Vec<u32> {
+        let mut canvas = Canvas::create(self.width, self.height, self.safe_zone);
+
+        let center_x = (self.width / 2) as i32;
+        let center_y = (self.height / 2) as i32;
+        for (index, tile) in self.tiles.iter().enumerate() {
+            let x = (index % self.width) as i32 - center_x;
+            let y = (index / self.width) as i32 - center_y;
+            canvas.draw_tile(x, y, if *tile == Tile::Gravel {0xffffff} else {0x43711d}, 1.0);
+        }
+
+        for road in self.roads.iter().filter(|road| road.is_drawable()) {
+            road.render(&mut canvas);
+        }
+
+        let safe_zone = &self.safe_zone;
+        canvas.draw_oval(safe_zone.center[0], safe_zone.center[1], safe_zone.width, safe_zone.height, 0x39a8e7, 0.5);
+
+        for (_name, wall) in self.walls.boundaries() {
+            wall.render(&mut canvas, 0x000000);
+        }
+
+        return canvas.pixels;
+    }
+
+    // rgba bytes where every tile becomes a scale x scale block of pixels
+    pub fn render_image(&self, scale: u32) -> Vec<u8> {
+        let pixels = self.render_pixels();
+        let scale = scale.max(1) as usize;
+        let image_width = self.width * scale;
+        let image_height = self.height * scale;
+
+        let mut image = vec![0; image_width * image_height * 4];
+        for (i, pixel) in image.chunks_exact_mut(4).enumerate() {
+            let x = (i % image_width) / scale;
+            let y = (i / image_width) / scale;
+
+            pixel.copy_from_slice(&pixels[y * self.width + x].to_be_bytes());
+        }
+
+        return image;
+    }
+
+    pub fn write_png<P: AsRef<Path>>(&self, path: P, scale: u32) -> std::io::Result<()> {
+        let scale = scale.max(1);
+        let writer = BufWriter::new(File::create(path)?);
+
+        let mut encoder = png::Encoder::new(writer, self.width as u32 * scale, self.height as u32 * scale);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.render_image(scale))?;
+
+        return Ok(());
+    }
+
+    pub fn write_svg<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        return svg::write_svg(self, path);
+    }
+
+    pub fn write_tiled<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        return tiled::write_tiled(self, path);
+    }
+
+    // without the tileset image, for writing many maps that share one
+    pub fn write_tiled_map<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        return tiled::write_tiled_map(self, path);
+    }
+
+    pub fn render(&self, frame: &mut [u8], frame_width: u32, frame_height: u32) {
+        let pixels = self.render_pixels();
+
+        for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+            let x = (i % frame_width as usize) as f64 / frame_width as f64;
+            let y = (i / frame_width as usize) as f64 / frame_height as f64;
+            let x = (x * self.width as f64) as usize;
+            let y = (y * self.height as f64) as usize;
+
+            let index = y * self.width + x;
+            let color = pixels[index];
+
+            pixel[0] = ((color & 0xff000000) >> 24) as u8;
+            pixel[1] = ((color & 0x00ff0000) >> 16) as u8;
+            pixel[2] = ((color & 0x0000ff00) >> 8) as u8;
+            pixel[3] = (color & 0x000000ff) as u8;
+        }
+    }
+}
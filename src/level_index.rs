@@ -0,0 +1,184 @@
+use crate::level::{LevelWalls, LevelZones, Zone};
+use crate::math_helper::{MathHelper, Segment};
+use rstar::{RTree, AABB};
+
+// every wall segment and zone polygon edge of a level, so point queries don't have to look at the whole level
+pub struct LevelIndex {
+    walls: RTree<Segment<()>>,
+    zone_edges: RTree<Segment<Zone>>,
+    max_x: f32,
+}
+
+impl LevelIndex {
+    pub fn create(walls: &LevelWalls, zones: &LevelZones) -> Self {
+        let mut segments = Vec::new();
+        for (_name, wall) in walls.boundaries() {
+            for index in 1..wall.lines.len() {
+                segments.push(Segment::create(wall.lines[index - 1], wall.lines[index], ()));
+            }
+        }
+
+        let mut zone_edges = Vec::new();
+        for (zone, polygon) in zones.iter() {
+            for index in 0..polygon.points.len() {
+                zone_edges.push(Segment::create(polygon.points[index], polygon.points[(index + 1) % polygon.points.len()], zone));
+            }
+        }
+
+        let max_x = zone_edges.iter().map(|edge| edge.points[0][0]).fold(f32::MIN, f32::max);
+
+        return LevelIndex {
+            walls: RTree::bulk_load(segments),
+            zone_edges: RTree::bulk_load(zone_edges),
+            max_x,
+        };
+    }
+
+    // casts a ray to the right and counts the crossings of each zone, only the edges the ray touches are visited
+    pub fn zone_at(&self, point: [f32; 2]) -> Zone {
+        if point[0] > self.max_x {
+            return Zone::Outside;
+        }
+
+        let ray = AABB::from_corners(point, [self.max_x, point[1]]);
+
+        let mut inside = [false; 4];
+        for edge in self.zone_edges.locate_in_envelope_intersecting(&ray) {
+            let a = edge.points[0];
+            let b = edge.points[1];
+            if (a[1] > point[1]) == (b[1] > point[1]) {
+                continue;
+            }
+
+            let x = a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]);
+            if point[0] < x {
+                let slot = &mut inside[LevelIndex::zone_slot(edge.data)];
+                *slot = !*slot;
+            }
+        }
+
+        // the safe zone wins where it overlaps the start of a tier
+        for zone in [Zone::Safe, Zone::T1, Zone::T2, Zone::T3] {
+            if inside[LevelIndex::zone_slot(zone)] {
+                return zone;
+            }
+        }
+
+        return Zone::Outside;
+    }
+
+    // nearest point on any wall and the distance to it
+    pub fn distance_to_wall(&self, point: [f32; 2]) -> ([f32; 2], f32) {
+        return match Segment::nearest(&self.walls, &point) {
+            Some(segment) => MathHelper::distance_to_line_segment(&segment.points, &point),
+            None => (point, f32::INFINITY),
+        };
+    }
+
+    // the first point along the line that hits a wall and the wall segment it hit
+    pub fn intersect_wall(&self, line: [[f32; 2]; 2]) -> Option<([f32; 2], [[f32; 2]; 2])> {
+        let min = [line[0][0].min(line[1][0]), line[0][1].min(line[1][1])];
+        let max = [line[0][0].max(line[1][0]), line[0][1].max(line[1][1])];
+
+        let mut nearest: Option<([f32; 2], [[f32; 2]; 2], f32)> = None;
+        for segment in self.walls.locate_in_envelope_intersecting(&AABB::from_corners(min, max)) {
+            let hit = match MathHelper::intersect_line_line(line, segment.points) {
+                Some(hit) => hit,
+                None => continue,
+            };
+
+            // nearly parallel lines can report hits that aren't on either of them
+            let (_nearest, distance) = MathHelper::distance_to_line_segment(&segment.points, &hit);
+            if distance > 1.0 {
+                continue;
+            }
+
+            let dx = hit[0] - line[0][0];
+            let dy = hit[1] - line[0][1];
+            let distance = dx * dx + dy * dy;
+            if nearest.is_none_or(|(_hit, _segment, nearest_distance)| distance < nearest_distance) {
+                nearest = Some((hit, segment.points, distance));
+            }
+        }
+
+        return nearest.map(|(hit, segment, _distance)| (hit, segment));
+    }
+
+    fn zone_slot(zone: Zone) -> usize {
+        return match zone {
+            Zone::Safe => 0,
+            Zone::T1 => 1,
+            Zone::T2 => 2,
+            Zone::T3 => 3,
+            Zone::Outside => unreachable!(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+    use crate::helpers::wall_section::WallSection;
+    use crate::helpers::zone_polygon::ZonePolygon;
+
+    // the index should give the same answers as checking every wall and polygon
+    #[test]
+    fn matches_brute_force() {
+        let level = crate::generate(5);
+        let index = LevelIndex::create(&level.walls, &level.zones);
+        let mut random = Random::create(5);
+
+        for _ in 0..500 {
+            let point = [random.next_range(-60000.0, 60000.0), random.next_range(-60000.0, 60000.0)];
+
+            let zone = level.zones.iter().into_iter()
+                .find(|(_zone, polygon)| polygon.contains(&point))
+                .map_or(Zone::Outside, |(zone, _polygon)| zone);
+            assert_eq!(index.zone_at(point), zone, "zone at {:?}", point);
+
+            let distance = level.walls.boundaries().iter()
+                .map(|(_name, wall)| wall.distance_to_wall(&point).1)
+                .fold(f32::INFINITY, f32::min);
+            assert!((index.distance_to_wall(point).1 - distance).abs() < 0.01, "distance at {:?}", point);
+        }
+    }
+
+    #[test]
+    fn intersect_wall_finds_the_nearest_hit() {
+        let square = WallSection {
+            lines: vec![[0.0, 0.0], [100.0, 0.0], [100.0, 100.0], [0.0, 100.0], [0.0, 0.0]],
+        };
+        let walls = LevelWalls {
+            t1_inner_1: square.clone(),
+            t1_inner_2: square.clone(),
+            t2_t1_inner: square.clone(),
+            t3_t2_inner: square.clone(),
+            outer_wall: square.clone(),
+            t1_upper_back_wall: square.clone(),
+            t1_lower_back_wall: square.clone(),
+            t2_back_wall: square.clone(),
+            t3_back_wall: square,
+        };
+        let polygon = ZonePolygon::from_ellipse([0.0, 0.0], 10.0, 10.0);
+        let zones = LevelZones {
+            safe_zone: polygon.clone(),
+            t1: polygon.clone(),
+            t2: polygon.clone(),
+            t3: polygon,
+        };
+        let index = LevelIndex::create(&walls, &zones);
+
+        let (hit, segment) = index.intersect_wall([[50.0, 50.0], [50.0, 200.0]]).unwrap();
+        assert!((hit[0] - 50.0).abs() < 0.01 && (hit[1] - 100.0).abs() < 0.01);
+        assert_eq!(segment, [[100.0, 100.0], [0.0, 100.0]]);
+
+        let (hit, _segment) = index.intersect_wall([[50.0, 200.0], [50.0, -100.0]]).unwrap();
+        assert!((hit[1] - 100.0).abs() < 0.01);
+
+        assert!(index.intersect_wall([[20.0, 20.0], [80.0, 80.0]]).is_none());
+        assert_eq!(index.distance_to_wall([50.0, 30.0]).1, 30.0);
+        assert_eq!(index.zone_at([0.0, 0.0]), Zone::Safe);
+        assert_eq!(index.zone_at([50.0, 50.0]), Zone::Outside);
+    }
+}
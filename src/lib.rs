@@ -0,0 +1,47 @@
+#![allow(clippy::needless_return)]
+
+pub mod generator;
+pub mod random;
+pub mod perlin;
+pub mod fractal_noise;
+pub mod domain_warp;
+pub mod worley;
+pub mod math_helper;
+pub mod helpers;
+pub mod level;
+pub mod canvas;
+pub mod config;
+pub mod export;
+mod level_index;
+pub mod road_network;
+
+pub use crate::generator::{Generator, NoiseLayer};
+pub use crate::helpers::wall_section::WallSection;
+pub use crate::helpers::road_segment::RoadSegment;
+pub use crate::helpers::road_cleanup::RoadCleanup;
+pub use crate::helpers::zone_polygon::ZonePolygon;
+pub use crate::perlin::Perlin;
+pub use crate::fractal_noise::{FractalNoise, FractalMode, NoiseBasis};
+pub use crate::domain_warp::DomainWarp;
+pub use crate::worley::{Worley, WorleySample};
+pub use crate::random::Random;
+pub use crate::math_helper::MathHelper;
+pub use crate::level::{Level, LevelWalls, LevelZones, LevelStats, SafeZone, Tile, WallPath, Zone};
+pub use crate::canvas::Canvas;
+pub use crate::config::{GeneratorConfig, RoadWallMode};
+pub use crate::road_network::{RoadNetwork, RoadNode, RoadNodeKind, RoadEdge, RoadPath, RoadPoint};
+
+pub const SCREEN_WIDTH: u32 = 1920;
+pub const SCREEN_HEIGHT: u32 = 1080;
+
+pub fn generate(seed: u64) -> Level {
+    let mut generator = Generator::with_seed(seed);
+
+    return generator.generate();
+}
+
+pub fn generate_with_config(seed: u64, config: GeneratorConfig) -> Level {
+    let mut generator = Generator::with_config(seed, config);
+
+    return generator.generate();
+}
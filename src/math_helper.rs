@@ -1,9 +1,49 @@
 use std::f32::consts::PI;
+use rstar::{RTree, RTreeObject, PointDistance, AABB};
 
 pub struct MathHelper {
 
 }
 
+// a line segment for an rtree, with whatever its owner needs to know about where it came from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Segment<T> {
+    pub points: [[f32; 2]; 2],
+    pub data: T,
+}
+
+impl<T> Segment<T> {
+    pub fn create(start: [f32; 2], end: [f32; 2], data: T) -> Self {
+        return Segment {
+            points: [start, end],
+            data,
+        };
+    }
+
+    // nearest_neighbor can prune the closest segment in this version of rstar, the iterator doesn't
+    pub fn nearest<'a>(tree: &'a RTree<Segment<T>>, point: &[f32; 2]) -> Option<&'a Segment<T>> {
+        return tree.nearest_neighbor_iter(point).next();
+    }
+}
+
+impl<T> RTreeObject for Segment<T> {
+    type Envelope = AABB<[f32; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        let min = [self.points[0][0].min(self.points[1][0]), self.points[0][1].min(self.points[1][1])];
+        let max = [self.points[0][0].max(self.points[1][0]), self.points[0][1].max(self.points[1][1])];
+
+        return AABB::from_corners(min, max);
+    }
+}
+
+impl<T> PointDistance for Segment<T> {
+    fn distance_2(&self, point: &[f32; 2]) -> f32 {
+        let (_nearest, distance) = MathHelper::distance_to_line_segment(&self.points, point);
+        return distance * distance;
+    }
+}
+
 impl MathHelper {
     pub fn radians_between_angles(from: f32, to: f32) -> f32 {
         if to < from {
@@ -60,18 +100,34 @@ impl MathHelper {
         let yd = y1 - y3;
         let xd = x1 - x3;
         let ua = ((x4 - x3) * yd - (y4 - y3) * xd) / d;
-        if ua < 0.0 || ua > 1.0 {
+        if !(0.0..=1.0).contains(&ua) {
             return None;
         }
 
         let ub = ((x2 - x1) * yd - (y2 - y1) * xd) / d;
-        if ub < 0.0 || ub > 1.0 {
+        if !(0.0..=1.0).contains(&ub) {
             return None;
         }
 
         return Some([x1 + (x2 - x1) * ua, y1 + (y2 - y1) * ua]);
     }
 
+    pub fn distance(first: &[f32; 2], second: &[f32; 2]) -> f32 {
+        let dx = second[0] - first[0];
+        let dy = second[1] - first[1];
+        return (dx * dx + dy * dy).sqrt();
+    }
+
+    // none for a zero length vector
+    pub fn normalize(vector: [f32; 2]) -> Option<[f32; 2]> {
+        let length = (vector[0] * vector[0] + vector[1] * vector[1]).sqrt();
+        if length == 0.0 {
+            return None;
+        }
+
+        return Some([vector[0] / length, vector[1] / length]);
+    }
+
     pub fn distance_to_line_segment(line: &[[f32; 2]; 2], point: &[f32; 2]) -> ([f32; 2], f32) {
         let line_dx = line[1][0] - line[0][0];
         let line_dy = line[1][1] - line[0][1];
@@ -102,16 +158,16 @@ impl MathHelper {
     pub fn distance_to_ellipse(center_x: f32, center_y: f32, semi_major: f32, semi_minor: f32, point: &[f32; 2]) -> ([f32; 2], f32) {
         let point = [point[0] - center_x, point[1] - center_y];
 
-        let mut px = point[0].abs();
-        let mut py = point[1].abs();
+        let px = point[0].abs();
+        let py = point[1].abs();
 
         let mut tx = 0.707;
         let mut ty = 0.707;
 
-        let mut a = semi_major;
-        let mut b = semi_minor;
+        let a = semi_major;
+        let b = semi_minor;
 
-        for x in 0..3 {
+        for _x in 0..3 {
             let x = a * tx;
             let y = b * ty;
 
@@ -127,11 +183,11 @@ impl MathHelper {
             let r = (ry * ry + rx * rx).sqrt();
             let q = (qy * qy + qx * qx).sqrt();
 
-            tx = (1.0 as f32).min((0.0 as f32).max((qx * r / q + ex) / a));
-            ty = (1.0 as f32).min((0.0 as f32).max((qy * r / q + ey) / b));
+            tx = 1.0_f32.min(0.0_f32.max((qx * r / q + ex) / a));
+            ty = 1.0_f32.min(0.0_f32.max((qy * r / q + ey) / b));
             let t = (ty * ty + tx * tx).sqrt();
-            tx = tx / t;
-            ty = ty / t;
+            tx /= t;
+            ty /= t;
         }
 
         let x = (a * tx).abs() * point[0].signum();
@@ -163,6 +219,18 @@ impl MathHelper {
         ];
     }
 
+    // evenly spaced points along the line no more than max_length apart, including the end but not the start
+    pub fn subdivide_line(start: [f32; 2], end: [f32; 2], max_length: f32) -> Vec<[f32; 2]> {
+        let dx = end[0] - start[0];
+        let dy = end[1] - start[1];
+        let steps = ((dx * dx + dy * dy).sqrt() / max_length).ceil().max(1.0) as usize;
+
+        return (1..=steps).map(|step| {
+            let t = step as f32 / steps as f32;
+            return [start[0] + dx * t, start[1] + dy * t];
+        }).collect();
+    }
+
     pub fn ease_in_out(t: f32) -> f32 {
         let p = 2.0 * t * t;
         return if t < 0.5 {p} else {-p + (4.0 * t) - 1.0};
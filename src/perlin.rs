@@ -1,3 +1,5 @@
+use crate::random::Random;
+
 #[derive(Copy, Clone)]
 struct Grad(f32, f32, f32);
 
@@ -26,14 +28,42 @@ pub struct Perlin {
     g2: f32,
     f3: f32,
     g3: f32,
+    // where the lattice starts, so differently seeded fields don't all cross zero at the same points
+    origin: [f32; 3],
 }
 
 impl Perlin {
-    pub fn seed(&mut self, seed: f32) {
-        self.seed_int((seed * 65536.0) as i32);
+    pub fn create(seed: u64) -> Self {
+        let mut perlin = Perlin::default();
+        perlin.seed(seed);
+        return perlin;
+    }
+
+    // a full shuffle of the permutation table, so every seed gets its own noise field
+    pub fn seed(&mut self, seed: u64) {
+        let mut random = Random::create(seed);
+        let mut p = self.p;
+        for i in (1..p.len()).rev() {
+            let j = (random.next_u32() % (i as u32 + 1)) as usize;
+            p.swap(i, j);
+        }
+
+        self.origin = [random.next_f32(), random.next_f32(), random.next_f32()];
+
+        for (i, v) in p.iter().enumerate() {
+            let v = *v as usize;
+
+            self.perm[i] = v;
+            self.perm[i + 256] = v;
+
+            self.grad_p[i] = self.grad_3[v % 12];
+            self.grad_p[i + 256] = self.grad_3[v % 12];
+        }
     }
 
-    pub fn perlin2(&mut self, x: f32, y: f32) -> f32 {
+    pub fn perlin2(&self, x: f32, y: f32) -> f32 {
+        let x = x + self.origin[0];
+        let y = y + self.origin[1];
         let x_floor = x.floor() as i32;
         let y_floor = y.floor() as i32;
         let x = x - x_floor as f32;
@@ -55,25 +85,139 @@ impl Perlin {
             self.fade(y));
     }
 
-    fn seed_int(&mut self, mut seed: i32) {
-        if seed < 256 {
-            seed |= seed << 8;
-        }
+    pub fn perlin3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let x = x + self.origin[0];
+        let y = y + self.origin[1];
+        let z = z + self.origin[2];
+        let x_floor = x.floor() as i32;
+        let y_floor = y.floor() as i32;
+        let z_floor = z.floor() as i32;
+        let x = x - x_floor as f32;
+        let y = y - y_floor as f32;
+        let z = z - z_floor as f32;
+
+        let x_floor = (x_floor & 0xff) as usize;
+        let y_floor = (y_floor & 0xff) as usize;
+        let z_floor = (z_floor & 0xff) as usize;
+
+        let n000 = self.grad_p[x_floor + self.perm[y_floor + self.perm[z_floor]]].dot3(x, y, z);
+        let n001 = self.grad_p[x_floor + self.perm[y_floor + self.perm[z_floor + 1]]].dot3(x, y, z - 1.0);
+        let n010 = self.grad_p[x_floor + self.perm[y_floor + 1 + self.perm[z_floor]]].dot3(x, y - 1.0, z);
+        let n011 = self.grad_p[x_floor + self.perm[y_floor + 1 + self.perm[z_floor + 1]]].dot3(x, y - 1.0, z - 1.0);
+        let n100 = self.grad_p[x_floor + 1 + self.perm[y_floor + self.perm[z_floor]]].dot3(x - 1.0, y, z);
+        let n101 = self.grad_p[x_floor + 1 + self.perm[y_floor + self.perm[z_floor + 1]]].dot3(x - 1.0, y, z - 1.0);
+        let n110 = self.grad_p[x_floor + 1 + self.perm[y_floor + 1 + self.perm[z_floor]]].dot3(x - 1.0, y - 1.0, z);
+        let n111 = self.grad_p[x_floor + 1 + self.perm[y_floor + 1 + self.perm[z_floor + 1]]].dot3(x - 1.0, y - 1.0, z - 1.0);
+
+        let u = self.fade(x);
+        let v = self.fade(y);
+        let w = self.fade(z);
+
+        return self.lerp(
+            self.lerp(self.lerp(n000, n100, u), self.lerp(n001, n101, u), w),
+            self.lerp(self.lerp(n010, n110, u), self.lerp(n011, n111, u), w),
+            v);
+    }
+
+    // roughly in [-1, 1] like perlin2, but without the grid aligned artifacts
+    pub fn simplex2(&self, x: f32, y: f32) -> f32 {
+        let x = x + self.origin[0];
+        let y = y + self.origin[1];
+
+        // skew the input space to find which simplex cell we're in
+        let s = (x + y) * self.f2;
+        let i = (x + s).floor();
+        let j = (y + s).floor();
+        let t = (i + j) * self.g2;
+        let x0 = x - i + t;
+        let y0 = y - j + t;
+
+        // the middle corner of the triangle, lower triangle is xy order and upper is yx order
+        let (i1, j1) = if x0 > y0 {(1, 0)} else {(0, 1)};
+
+        let x1 = x0 - i1 as f32 + self.g2;
+        let y1 = y0 - j1 as f32 + self.g2;
+        let x2 = x0 - 1.0 + 2.0 * self.g2;
+        let y2 = y0 - 1.0 + 2.0 * self.g2;
 
-        for i in 0..256 {
-            let v;
-            if i & 1 == 1 {
-                v = (self.p[i] ^ (seed & 255)) as usize;
+        let i = (i as i32 & 0xff) as usize;
+        let j = (j as i32 & 0xff) as usize;
+        let gi0 = self.grad_p[i + self.perm[j]];
+        let gi1 = self.grad_p[i + i1 + self.perm[j + j1]];
+        let gi2 = self.grad_p[i + 1 + self.perm[j + 1]];
+
+        let n0 = self.simplex_corner(0.5 - x0 * x0 - y0 * y0, gi0.dot2(x0, y0));
+        let n1 = self.simplex_corner(0.5 - x1 * x1 - y1 * y1, gi1.dot2(x1, y1));
+        let n2 = self.simplex_corner(0.5 - x2 * x2 - y2 * y2, gi2.dot2(x2, y2));
+
+        return 70.0 * (n0 + n1 + n2);
+    }
+
+    pub fn simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let x = x + self.origin[0];
+        let y = y + self.origin[1];
+        let z = z + self.origin[2];
+
+        let s = (x + y + z) * self.f3;
+        let i = (x + s).floor();
+        let j = (y + s).floor();
+        let k = (z + s).floor();
+        let t = (i + j + k) * self.g3;
+        let x0 = x - i + t;
+        let y0 = y - j + t;
+        let z0 = z - k + t;
+
+        // offsets for the second and third corners of the tetrahedron
+        let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+            if y0 >= z0 {
+                (1, 0, 0, 1, 1, 0)
+            } else if x0 >= z0 {
+                (1, 0, 0, 1, 0, 1)
             } else {
-                v = (self.p[i] ^ ((seed >> 8) & 255)) as usize;
+                (0, 0, 1, 1, 0, 1)
             }
+        } else if y0 < z0 {
+            (0, 0, 1, 0, 1, 1)
+        } else if x0 < z0 {
+            (0, 1, 0, 0, 1, 1)
+        } else {
+            (0, 1, 0, 1, 1, 0)
+        };
 
-            self.perm[i] = v;
-            self.perm[i + 256] = v;
+        let x1 = x0 - i1 as f32 + self.g3;
+        let y1 = y0 - j1 as f32 + self.g3;
+        let z1 = z0 - k1 as f32 + self.g3;
+        let x2 = x0 - i2 as f32 + 2.0 * self.g3;
+        let y2 = y0 - j2 as f32 + 2.0 * self.g3;
+        let z2 = z0 - k2 as f32 + 2.0 * self.g3;
+        let x3 = x0 - 1.0 + 3.0 * self.g3;
+        let y3 = y0 - 1.0 + 3.0 * self.g3;
+        let z3 = z0 - 1.0 + 3.0 * self.g3;
 
-            self.grad_p[i] = self.grad_3[v % 12];
-            self.grad_p[i + 256] = self.grad_3[v % 12];
+        let i = (i as i32 & 0xff) as usize;
+        let j = (j as i32 & 0xff) as usize;
+        let k = (k as i32 & 0xff) as usize;
+        let gi0 = self.grad_p[i + self.perm[j + self.perm[k]]];
+        let gi1 = self.grad_p[i + i1 + self.perm[j + j1 + self.perm[k + k1]]];
+        let gi2 = self.grad_p[i + i2 + self.perm[j + j2 + self.perm[k + k2]]];
+        let gi3 = self.grad_p[i + 1 + self.perm[j + 1 + self.perm[k + 1]]];
+
+        let n0 = self.simplex_corner(0.6 - x0 * x0 - y0 * y0 - z0 * z0, gi0.dot3(x0, y0, z0));
+        let n1 = self.simplex_corner(0.6 - x1 * x1 - y1 * y1 - z1 * z1, gi1.dot3(x1, y1, z1));
+        let n2 = self.simplex_corner(0.6 - x2 * x2 - y2 * y2 - z2 * z2, gi2.dot3(x2, y2, z2));
+        let n3 = self.simplex_corner(0.6 - x3 * x3 - y3 * y3 - z3 * z3, gi3.dot3(x3, y3, z3));
+
+        return 32.0 * (n0 + n1 + n2 + n3);
+    }
+
+    // the contribution of one simplex corner, falling off to nothing at the radius
+    fn simplex_corner(&self, t: f32, dot: f32) -> f32 {
+        if t < 0.0 {
+            return 0.0;
         }
+
+        let t = t * t;
+        return t * t * dot;
     }
 
     fn fade(&self, t: f32) -> f32 {
@@ -110,10 +254,54 @@ impl Default for Perlin {
                 Grad(1.0,0.0,1.0), Grad(-1.0,0.0,1.0), Grad(1.0,0.0,-1.0), Grad(-1.0,0.0,-1.0),
                 Grad(0.0,1.0,1.0), Grad(0.0,-1.0,1.0), Grad(0.0,1.0,-1.0), Grad(0.0,-1.0,-1.0),
             ],
-            f2: 0.5 * ((3.0 as f32).sqrt() as f32 - 1.0),
-            g2: (3.0 - (3.0 as f32).sqrt() as f32) / 6.0,
+            f2: 0.5 * (3.0_f32.sqrt() - 1.0),
+            g2: (3.0 - 3.0_f32.sqrt()) / 6.0,
             f3: 1.0 / 3.0,
             g3: 1.0 / 6.0,
+            origin: [0.0, 0.0, 0.0],
         };
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_field() {
+        let noise = Perlin::create(1);
+        let other = Perlin::create(1);
+        let different = Perlin::create(2);
+
+        assert_eq!(noise.perlin2(0.3, 0.6), other.perlin2(0.3, 0.6));
+        assert_ne!(noise.perlin2(0.3, 0.6), different.perlin2(0.3, 0.6));
+    }
+
+    #[test]
+    fn values_stay_in_range() {
+        let noise = Perlin::create(3);
+        for index in 0..1000 {
+            let x = index as f32 * 0.137 - 50.0;
+            let y = index as f32 * -0.071 + 20.0;
+            let z = index as f32 * 0.029;
+
+            for value in [noise.perlin2(x, y), noise.perlin3(x, y, z), noise.simplex2(x, y), noise.simplex3(x, y, z)] {
+                assert!((-1.0..=1.0).contains(&value), "{} at {} {} {}", value, x, y, z);
+            }
+        }
+    }
+
+    #[test]
+    fn fields_are_continuous() {
+        let noise = Perlin::create(4);
+        for index in 0..100 {
+            let x = index as f32 * 0.31;
+            let y = index as f32 * 0.17;
+
+            assert!((noise.perlin2(x, y) - noise.perlin2(x + 0.001, y)).abs() < 0.01);
+            assert!((noise.simplex2(x, y) - noise.simplex2(x, y + 0.001)).abs() < 0.02);
+            assert!((noise.perlin3(x, y, 1.0) - noise.perlin3(x, y, 1.001)).abs() < 0.01);
+            assert!((noise.simplex3(x, y, 1.0) - noise.simplex3(x + 0.001, y, 1.0)).abs() < 0.02);
+        }
+    }
+}
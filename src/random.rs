@@ -1,19 +1,130 @@
+const PCG_MULTIPLIER: u64 = 6364136223846793005;
+
+// pcg32 (xsh rr), 64 bits of state and a 2^64 period per stream
+// the seed picks both the starting state and the stream so nearby seeds don't share sequences
 pub struct Random {
-    seed: i64,
+    state: u64,
+    increment: u64,
+    // what forks are derived from, never advances so forking doesn't depend on how much has been drawn
+    key: u64,
 }
 
 impl Random {
-    pub fn next(&mut self) -> f32 {
-        self.seed = (self.seed * 9301 + 49297) % 233280;
+    pub fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.state = state.wrapping_mul(PCG_MULTIPLIER).wrapping_add(self.increment);
+
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        let rotation = (state >> 59) as u32;
+        return xorshifted.rotate_right(rotation);
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        return ((self.next_u32() as u64) << 32) | self.next_u32() as u64;
+    }
+
+    // in [0, 1), only the top 24 bits are used so every value is exactly representable
+    pub fn next_f32(&mut self) -> f32 {
+        return (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32;
+    }
+
+    // in [min, max)
+    pub fn next_range(&mut self, min: f32, max: f32) -> f32 {
+        return min + (max - min) * self.next_f32();
+    }
+
+    // true with a probability of p
+    pub fn next_bool(&mut self, p: f32) -> bool {
+        return self.next_f32() < p;
+    }
+
+    // an independent stream for the label, the same label always gives the same stream for the same seed
+    pub fn fork(&self, label: &str) -> Random {
+        // fnv-1a
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in label.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+
+        return Random::create(Random::split_mix(self.key ^ hash));
+    }
 
-        return (self.seed as f64 / 233280.0) as f32;
+    fn split_mix(value: u64) -> u64 {
+        let mut value = value.wrapping_add(0x9e3779b97f4a7c15);
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+        return value ^ (value >> 31);
     }
 }
 
 impl Random {
-    pub fn create(seed: i64) -> Self {
-        return Random {
-            seed: seed.abs(),
+    pub fn create(seed: u64) -> Self {
+        let key = Random::split_mix(seed);
+
+        // the increment has to be odd
+        let mut random = Random {
+            state: 0,
+            increment: (Random::split_mix(key) << 1) | 1,
+            key,
+        };
+
+        random.next_u32();
+        random.state = random.state.wrapping_add(key);
+        random.next_u32();
+
+        return random;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut random = Random::create(42);
+        let mut other = Random::create(42);
+        for _ in 0..100 {
+            assert_eq!(random.next_u32(), other.next_u32());
+        }
+
+        // nearby seeds don't give shifted copies of the same sequence
+        let mut first = Random::create(1);
+        let first: Vec<u32> = (0..8).map(|_| first.next_u32()).collect();
+        let mut nearby = Random::create(2);
+        let nearby: Vec<u32> = (0..8).map(|_| nearby.next_u32()).collect();
+        assert!(first.iter().all(|value| !nearby.contains(value)));
+    }
+
+    #[test]
+    fn forks_are_stable_and_independent() {
+        let mut random = Random::create(7);
+        let fork = random.fork("walls").next_u64();
+
+        // drawing from the parent doesn't change its forks
+        random.next_u64();
+        assert_eq!(random.fork("walls").next_u64(), fork);
+        assert_ne!(random.fork("roads").next_u64(), fork);
+        assert_ne!(Random::create(8).fork("walls").next_u64(), fork);
+    }
+
+    #[test]
+    fn ranges() {
+        let mut random = Random::create(3);
+        let mut total = 0.0;
+        for _ in 0..10000 {
+            let value = random.next_f32();
+            assert!((0.0..1.0).contains(&value));
+            total += value;
+
+            let value = random.next_range(-5.0, 5.0);
+            assert!((-5.0..5.0).contains(&value));
         }
+
+        // roughly uniform
+        assert!((total / 10000.0 - 0.5).abs() < 0.02);
+        assert!(!random.next_bool(0.0));
+        assert!(random.next_bool(1.0));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,675 @@
+use crate::helpers::road_segment::RoadSegment;
+use crate::level::SafeZone;
+use crate::math_helper::{MathHelper, Segment};
+use rstar::{RTree, RTreeObject, PointDistance, AABB};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+// intersections have to be this close to both segments, near parallel segments can report points far off either one
+pub(crate) const CROSSING_EPSILON: f32 = 1.0;
+// roads starting inside the safe zone scaled by this much are joined to its center
+pub(crate) const SAFE_ZONE_MARGIN: f32 = 1.1;
+// a road this wide costs twice its length to travel, wider roads get closer to just their length
+const WIDTH_PENALTY: f32 = 40.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RoadNodeKind {
+    // the middle of the safe zone, every road leaving the safe zone is connected to it
+    SafeZone,
+    // a road starting or stopping without meeting another road
+    End,
+    // a road splitting into two narrower roads
+    Split,
+    // roads crossing or one road running into another
+    Crossing,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RoadNode {
+    pub position: [f32; 2],
+    pub kind: RoadNodeKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoadEdge {
+    pub nodes: [usize; 2],
+    // index into Level::roads, none for the connections through the safe zone
+    pub road: Option<usize>,
+    pub points: Vec<[f32; 2]>,
+    pub length: f32,
+    // average thickness of the road along the edge
+    pub width: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoadPath {
+    pub nodes: Vec<usize>,
+    pub edges: Vec<usize>,
+    pub points: Vec<[f32; 2]>,
+    pub length: f32,
+    // the length weighted by how narrow the roads along the way are
+    pub cost: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RoadPoint {
+    pub edge: usize,
+    pub position: [f32; 2],
+    pub distance: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct NodePoint {
+    index: usize,
+    position: [f32; 2],
+}
+
+impl RTreeObject for NodePoint {
+    type Envelope = AABB<[f32; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        return AABB::from_point(self.position);
+    }
+}
+
+impl PointDistance for NodePoint {
+    fn distance_2(&self, point: &[f32; 2]) -> f32 {
+        let dx = self.position[0] - point[0];
+        let dy = self.position[1] - point[1];
+        return dx * dx + dy * dy;
+    }
+}
+
+// a node waiting to be visited by the path search, ordered so the binary heap pops the cheapest first
+#[derive(Copy, Clone, Debug)]
+struct OpenNode {
+    estimate: f32,
+    node: usize,
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        return other.estimate.total_cmp(&self.estimate).then_with(|| other.node.cmp(&self.node));
+    }
+}
+
+// the roads of a level as a graph, nodes are road ends, splits and crossings and edges are the roads between them
+pub struct RoadNetwork {
+    pub nodes: Vec<RoadNode>,
+    pub edges: Vec<RoadEdge>,
+    // edge indexes touching each node
+    adjacency: Vec<Vec<usize>>,
+    // nodes that share a component can reach each other
+    components: Vec<usize>,
+    // every segment of every road edge tagged with its edge, for finding the closest point on a road
+    edge_lines: RTree<Segment<usize>>,
+}
+
+impl RoadNetwork {
+    pub fn create(roads: &[RoadSegment], safe_zone: &SafeZone) -> Self {
+        let mut network = RoadNetwork {
+            nodes: vec![RoadNode {
+                position: safe_zone.center,
+                kind: RoadNodeKind::SafeZone,
+            }],
+            edges: Vec::new(),
+            adjacency: Vec::new(),
+            components: Vec::new(),
+            edge_lines: RTree::new(),
+        };
+
+        // road ends and crossings, so points close together end up as the same node
+        let mut node_points = RTree::new();
+
+        let polylines: Vec<Vec<([f32; 2], f32)>> = roads.iter().map(|road| {
+            return road.points.iter().map(|point| ([point.x, point.y], point.thickness)).collect();
+        }).collect();
+
+        // each road is cut into edges at these (segment index + progress along the segment, node) pairs
+        let mut cuts: Vec<Vec<(f32, usize)>> = vec![Vec::new(); polylines.len()];
+
+        // ends first so the children of a split, which start next to where their parent ended, join it
+        for (road, polyline) in polylines.iter().enumerate() {
+            if let Some((point, thickness)) = polyline.last() {
+                let node = network.add_node(&mut node_points, *point, *thickness / 2.0, RoadNodeKind::End);
+                cuts[road].push(((polyline.len() - 1) as f32, node));
+            }
+        }
+
+        let mut safe_zone_roads = Vec::new();
+        for (road, polyline) in polylines.iter().enumerate() {
+            if let Some((point, thickness)) = polyline.first() {
+                let node_count = network.nodes.len();
+                let node = network.add_node(&mut node_points, *point, *thickness / 2.0, RoadNodeKind::End);
+                if node < node_count && network.nodes[node].kind == RoadNodeKind::End {
+                    network.nodes[node].kind = RoadNodeKind::Split;
+                }
+
+                cuts[road].push((0.0, node));
+
+                let is_inside = MathHelper::is_point_inside_ellipse(*point, safe_zone.center, [safe_zone.width * SAFE_ZONE_MARGIN, safe_zone.height * SAFE_ZONE_MARGIN]);
+                if is_inside && !safe_zone_roads.iter().any(|(existing, _thickness)| *existing == node) {
+                    safe_zone_roads.push((node, *thickness));
+                }
+            }
+        }
+
+        // crossings between any two segments that aren't neighbours on the same road
+        // every segment is tagged with its road and the index of its first point
+        let mut lines = Vec::new();
+        for (road, polyline) in polylines.iter().enumerate() {
+            for segment in 1..polyline.len() {
+                lines.push(Segment::create(polyline[segment - 1].0, polyline[segment].0, (road, segment - 1)));
+            }
+        }
+
+        let line_tree = RTree::bulk_load(lines.clone());
+        for line in &lines {
+            let (road, segment) = line.data;
+            for other in line_tree.locate_in_envelope_intersecting(&line.envelope()) {
+                let (other_road, other_segment) = other.data;
+                if other.data <= line.data {
+                    continue;
+                }
+
+                if other_road == road && other_segment == segment + 1 {
+                    continue;
+                }
+
+                let point = match MathHelper::intersect_line_line(line.points, other.points) {
+                    Some(point) => point,
+                    None => continue,
+                };
+
+                let (_nearest, distance1) = MathHelper::distance_to_line_segment(&line.points, &point);
+                let (_nearest, distance2) = MathHelper::distance_to_line_segment(&other.points, &point);
+                if distance1 > CROSSING_EPSILON || distance2 > CROSSING_EPSILON {
+                    continue;
+                }
+
+                // only exact crossings are merged, wide roads running side by side would otherwise collapse into one node
+                let node = network.add_node(&mut node_points, point, CROSSING_EPSILON, RoadNodeKind::Crossing);
+                if network.nodes[node].kind == RoadNodeKind::End {
+                    network.nodes[node].kind = RoadNodeKind::Crossing;
+                }
+
+                cuts[road].push((segment as f32 + RoadNetwork::progress_along(&line.points, &point), node));
+                cuts[other_road].push((other_segment as f32 + RoadNetwork::progress_along(&other.points, &point), node));
+            }
+        }
+
+        // roads stopping right on another road, the crossing test can miss these by a rounding error
+        for (road, polyline) in polylines.iter().enumerate() {
+            let endpoints = [(0.0, polyline.first()), (polyline.len().saturating_sub(1) as f32, polyline.last())];
+            for (cut, endpoint) in endpoints {
+                let point = match endpoint {
+                    Some((point, _thickness)) => *point,
+                    None => continue,
+                };
+                let node = match cuts[road].iter().find(|(existing, _node)| *existing == cut) {
+                    Some((_cut, node)) => *node,
+                    None => continue,
+                };
+
+                let envelope = AABB::from_corners(
+                    [point[0] - CROSSING_EPSILON, point[1] - CROSSING_EPSILON],
+                    [point[0] + CROSSING_EPSILON, point[1] + CROSSING_EPSILON]);
+                for other in line_tree.locate_in_envelope_intersecting(&envelope) {
+                    let (other_road, other_segment) = other.data;
+                    if other_road == road {
+                        continue;
+                    }
+
+                    let (_nearest, distance) = MathHelper::distance_to_line_segment(&other.points, &point);
+                    if distance > CROSSING_EPSILON {
+                        continue;
+                    }
+
+                    if network.nodes[node].kind == RoadNodeKind::End {
+                        network.nodes[node].kind = RoadNodeKind::Crossing;
+                    }
+
+                    cuts[other_road].push((other_segment as f32 + RoadNetwork::progress_along(&other.points, &point), node));
+                }
+            }
+        }
+
+        for (node, thickness) in safe_zone_roads {
+            let position = network.nodes[node].position;
+            network.edges.push(RoadEdge {
+                nodes: [0, node],
+                road: None,
+                points: vec![safe_zone.center, position],
+                length: MathHelper::distance(&safe_zone.center, &position),
+                width: thickness,
+            });
+        }
+
+        for (road, mut road_cuts) in cuts.into_iter().enumerate() {
+            road_cuts.sort_by(|first, second| first.0.partial_cmp(&second.0).unwrap());
+            road_cuts.dedup_by(|second, first| first.1 == second.1);
+
+            for window in road_cuts.windows(2) {
+                let (start, start_node) = window[0];
+                let (end, end_node) = window[1];
+                if start_node == end_node {
+                    continue;
+                }
+
+                let edge = network.create_edge(road, &polylines[road], start, start_node, end, end_node);
+                network.edges.push(edge);
+            }
+        }
+
+        let mut edge_lines = Vec::new();
+        for (edge, road_edge) in network.edges.iter().enumerate() {
+            if road_edge.road.is_none() {
+                continue;
+            }
+
+            for pair in road_edge.points.windows(2) {
+                edge_lines.push(Segment::create(pair[0], pair[1], edge));
+            }
+        }
+        network.edge_lines = RTree::bulk_load(edge_lines);
+
+        network.update_components();
+
+        return network;
+    }
+
+    // the edge index and the node on the other side of it for every edge touching the node
+    pub fn get_neighbors(&self, node: usize) -> Vec<(usize, usize)> {
+        return self.adjacency[node].iter().map(|edge| {
+            let nodes = self.edges[*edge].nodes;
+            return (*edge, if nodes[0] == node {nodes[1]} else {nodes[0]});
+        }).collect();
+    }
+
+    pub fn get_degree(&self, node: usize) -> usize {
+        return self.adjacency[node].len();
+    }
+
+    // nodes where three or more roads meet
+    pub fn get_junctions(&self) -> Vec<usize> {
+        return (0..self.nodes.len()).filter(|node| self.get_degree(*node) >= 3).collect();
+    }
+
+    pub fn get_component(&self, node: usize) -> usize {
+        return self.components[node];
+    }
+
+    pub fn get_component_count(&self) -> usize {
+        return self.components.iter().max().map_or(0, |component| component + 1);
+    }
+
+    pub fn is_connected(&self, first: usize, second: usize) -> bool {
+        return self.components[first] == self.components[second];
+    }
+
+    pub fn get_safe_zone_node(&self) -> usize {
+        return 0;
+    }
+
+    // the closest point on any road, the connections through the safe zone don't count
+    pub fn nearest_road_point(&self, x: f32, y: f32) -> Option<RoadPoint> {
+        let point = [x, y];
+
+        let line = Segment::nearest(&self.edge_lines, &point)?;
+        let (position, distance) = MathHelper::distance_to_line_segment(&line.points, &point);
+
+        return Some(RoadPoint {
+            edge: line.data,
+            position,
+            distance,
+        });
+    }
+
+    // what it takes to travel an edge, narrow roads cost more than their length
+    pub fn get_edge_cost(&self, edge: usize) -> f32 {
+        let edge = &self.edges[edge];
+        return edge.length * (1.0 + WIDTH_PENALTY / edge.width.max(1.0));
+    }
+
+    // a* over the edges weighted by get_edge_cost, none if the nodes aren't connected
+    pub fn shortest_path(&self, from: usize, to: usize) -> Option<RoadPath> {
+        if !self.is_connected(from, to) {
+            return None;
+        }
+
+        // every edge costs at least its length, so the straight line distance never overestimates
+        let goal = self.nodes[to].position;
+        let heuristic = |node: usize| MathHelper::distance(&self.nodes[node].position, &goal);
+
+        let mut costs = vec![f32::INFINITY; self.nodes.len()];
+        let mut came_from: Vec<Option<(usize, usize)>> = vec![None; self.nodes.len()];
+        let mut open = BinaryHeap::new();
+
+        costs[from] = 0.0;
+        open.push(OpenNode {
+            estimate: heuristic(from),
+            node: from,
+        });
+
+        while let Some(OpenNode { estimate, node }) = open.pop() {
+            if node == to {
+                break;
+            }
+
+            // a cheaper way here was already visited
+            if estimate > costs[node] + heuristic(node) {
+                continue;
+            }
+
+            for (edge, neighbor) in self.get_neighbors(node) {
+                let cost = costs[node] + self.get_edge_cost(edge);
+                if cost < costs[neighbor] {
+                    costs[neighbor] = cost;
+                    came_from[neighbor] = Some((edge, node));
+                    open.push(OpenNode {
+                        estimate: cost + heuristic(neighbor),
+                        node: neighbor,
+                    });
+                }
+            }
+        }
+
+        if costs[to].is_infinite() {
+            return None;
+        }
+
+        let mut nodes = vec![to];
+        let mut edges = Vec::new();
+        let mut node = to;
+        while let Some((edge, previous)) = came_from[node] {
+            edges.push(edge);
+            nodes.push(previous);
+            node = previous;
+        }
+        nodes.reverse();
+        edges.reverse();
+
+        let mut points = vec![self.nodes[from].position];
+        for (index, edge) in edges.iter().enumerate() {
+            let road_edge = &self.edges[*edge];
+            let edge_points: Vec<[f32; 2]> = if road_edge.nodes[0] == nodes[index] {
+                road_edge.points.clone()
+            } else {
+                road_edge.points.iter().rev().cloned().collect()
+            };
+
+            points.extend_from_slice(&edge_points[1..]);
+        }
+
+        return Some(RoadPath {
+            length: edges.iter().map(|edge| self.edges[*edge].length).sum(),
+            cost: costs[to],
+            nodes,
+            edges,
+            points,
+        });
+    }
+
+    // reuses a node within the distance instead of adding one
+    fn add_node(&mut self, node_points: &mut RTree<NodePoint>, position: [f32; 2], distance: f32, kind: RoadNodeKind) -> usize {
+        let distance = distance.max(CROSSING_EPSILON);
+        if let Some(existing) = node_points.nearest_neighbor_iter(&position).next() {
+            if existing.distance_2(&position) <= distance * distance {
+                return existing.index;
+            }
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(RoadNode {
+            position,
+            kind,
+        });
+        node_points.insert(NodePoint {
+            index,
+            position,
+        });
+
+        return index;
+    }
+
+    // the part of the polyline between the two cuts, starting and ending exactly on the nodes
+    fn create_edge(&self, road: usize, polyline: &[([f32; 2], f32)], start: f32, start_node: usize, end: f32, end_node: usize) -> RoadEdge {
+        let mut points = vec![self.nodes[start_node].position];
+        let mut thickness_total = 0.0;
+        let mut thickness_count = 0;
+        for (index, (point, thickness)) in polyline.iter().enumerate() {
+            if index as f32 > start && (index as f32) < end {
+                points.push(*point);
+            }
+
+            if index as f32 >= start.floor() && index as f32 <= end.ceil() {
+                thickness_total += thickness;
+                thickness_count += 1;
+            }
+        }
+        points.push(self.nodes[end_node].position);
+
+        let length = points.windows(2).map(|pair| MathHelper::distance(&pair[0], &pair[1])).sum();
+
+        return RoadEdge {
+            nodes: [start_node, end_node],
+            road: Some(road),
+            points,
+            length,
+            width: thickness_total / thickness_count.max(1) as f32,
+        };
+    }
+
+    fn update_components(&mut self) {
+        self.adjacency = vec![Vec::new(); self.nodes.len()];
+        for (index, edge) in self.edges.iter().enumerate() {
+            self.adjacency[edge.nodes[0]].push(index);
+            self.adjacency[edge.nodes[1]].push(index);
+        }
+
+        self.components = vec![usize::MAX; self.nodes.len()];
+        let mut component = 0;
+        for start in 0..self.nodes.len() {
+            if self.components[start] != usize::MAX {
+                continue;
+            }
+
+            let mut stack = vec![start];
+            self.components[start] = component;
+            while let Some(node) = stack.pop() {
+                for (_edge, neighbor) in self.get_neighbors(node) {
+                    if self.components[neighbor] == usize::MAX {
+                        self.components[neighbor] = component;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+
+            component += 1;
+        }
+    }
+
+    fn progress_along(line: &[[f32; 2]; 2], point: &[f32; 2]) -> f32 {
+        let length = MathHelper::distance(&line[0], &line[1]);
+        if length == 0.0 {
+            return 0.0;
+        }
+
+        return (MathHelper::distance(&line[0], point) / length).min(1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::road_segment::Point;
+
+    fn road(points: &[[f32; 2]], thickness: f32) -> RoadSegment {
+        return RoadSegment::from_points(points.iter().map(|point| Point {x: point[0], y: point[1], thickness}).collect());
+    }
+
+    fn safe_zone() -> SafeZone {
+        return SafeZone {
+            center: [0.0, 0.0],
+            width: 200.0,
+            height: 200.0,
+        };
+    }
+
+    fn node_at(network: &RoadNetwork, position: [f32; 2]) -> usize {
+        return (0..network.nodes.len())
+            .find(|node| MathHelper::distance(&network.nodes[*node].position, &position) < 0.01)
+            .unwrap_or_else(|| panic!("no node at {:?}", position));
+    }
+
+    #[test]
+    fn crossing_roads() {
+        let roads = [
+            road(&[[1000.0, 0.0], [3000.0, 0.0]], 40.0),
+            road(&[[2000.0, -1000.0], [2000.0, 1000.0]], 40.0),
+        ];
+        let network = RoadNetwork::create(&roads, &safe_zone());
+
+        let crossing = node_at(&network, [2000.0, 0.0]);
+        assert_eq!(network.nodes[crossing].kind, RoadNodeKind::Crossing);
+        assert_eq!(network.get_degree(crossing), 4);
+        assert_eq!(network.edges.len(), 4);
+        assert_eq!(network.get_junctions(), vec![crossing]);
+    }
+
+    #[test]
+    fn road_ending_on_another_road_is_a_crossing() {
+        let roads = [
+            road(&[[1000.0, 0.0], [3000.0, 0.0]], 40.0),
+            road(&[[2000.0, 1000.0], [2000.0, 0.0]], 40.0),
+        ];
+        let network = RoadNetwork::create(&roads, &safe_zone());
+
+        let junction = node_at(&network, [2000.0, 0.0]);
+        assert_eq!(network.nodes[junction].kind, RoadNodeKind::Crossing);
+        assert_eq!(network.get_degree(junction), 3);
+        assert_eq!(network.get_component_count(), 2);
+    }
+
+    #[test]
+    fn split_roads_and_the_safe_zone() {
+        let roads = [
+            road(&[[50.0, 0.0], [1000.0, 0.0]], 80.0),
+            road(&[[1000.0, 0.0], [2000.0, 500.0]], 40.0),
+            road(&[[1000.0, 0.0], [2000.0, -500.0]], 40.0),
+        ];
+        let network = RoadNetwork::create(&roads, &safe_zone());
+
+        let split = node_at(&network, [1000.0, 0.0]);
+        assert_eq!(network.nodes[split].kind, RoadNodeKind::Split);
+        assert_eq!(network.get_degree(split), 3);
+
+        // the road starting inside the safe zone is joined to its center
+        let start = node_at(&network, [50.0, 0.0]);
+        let safe_zone_node = network.get_safe_zone_node();
+        assert!(network.get_neighbors(safe_zone_node).iter().any(|(_edge, node)| *node == start));
+        assert_eq!(network.get_component_count(), 1);
+
+        let end = node_at(&network, [2000.0, 500.0]);
+        assert_eq!(network.nodes[end].kind, RoadNodeKind::End);
+        assert!(network.is_connected(safe_zone_node, end));
+    }
+
+    #[test]
+    fn edges_follow_the_road() {
+        let roads = [road(&[[1000.0, 0.0], [1500.0, 500.0], [2000.0, 0.0]], 30.0)];
+        let network = RoadNetwork::create(&roads, &safe_zone());
+
+        assert_eq!(network.edges.len(), 1);
+        let edge = &network.edges[0];
+        assert_eq!(edge.points, vec![[1000.0, 0.0], [1500.0, 500.0], [2000.0, 0.0]]);
+        assert_eq!(edge.road, Some(0));
+        assert_eq!(edge.width, 30.0);
+        assert!((edge.length - 2.0 * 500.0 * 2.0f32.sqrt()).abs() < 0.01);
+    }
+
+    #[test]
+    fn generated_roads_reach_the_safe_zone() {
+        let level = crate::generate(2);
+        let network = level.get_road_network();
+
+        assert_eq!(network.get_component_count(), 1);
+        assert!(network.edges.iter().all(|edge| edge.length.is_finite() && edge.points.len() >= 2));
+    }
+
+    // a narrow direct road and a wide detour between the same two nodes, and a road off on its own
+    fn detour_network() -> RoadNetwork {
+        let roads = [
+            road(&[[1000.0, 0.0], [3000.0, 0.0]], 10.0),
+            road(&[[1000.0, 0.0], [2000.0, 1000.0]], 400.0),
+            road(&[[2000.0, 1000.0], [3000.0, 0.0]], 400.0),
+            road(&[[5000.0, 5000.0], [6000.0, 5000.0]], 40.0),
+        ];
+
+        return RoadNetwork::create(&roads, &safe_zone());
+    }
+
+    #[test]
+    fn shortest_path_prefers_wide_roads() {
+        let network = detour_network();
+        let from = node_at(&network, [1000.0, 0.0]);
+        let to = node_at(&network, [3000.0, 0.0]);
+
+        // the direct road costs 2000 * (1 + 40 / 10), the detour 2828 * (1 + 40 / 400)
+        let path = network.shortest_path(from, to).unwrap();
+        let detour = 2.0 * 1000.0 * 2.0f32.sqrt();
+        assert_eq!(path.nodes, vec![from, node_at(&network, [2000.0, 1000.0]), to]);
+        assert!((path.length - detour).abs() < 0.01);
+        assert!((path.cost - detour * 1.1).abs() < 0.01);
+        assert_eq!(path.cost, path.edges.iter().map(|edge| network.get_edge_cost(*edge)).sum::<f32>());
+    }
+
+    #[test]
+    fn shortest_path_walks_edges_backwards() {
+        let network = detour_network();
+        let from = node_at(&network, [3000.0, 0.0]);
+        let to = node_at(&network, [1000.0, 0.0]);
+
+        let path = network.shortest_path(from, to).unwrap();
+        assert_eq!(path.points, vec![[3000.0, 0.0], [2000.0, 1000.0], [1000.0, 0.0]]);
+    }
+
+    #[test]
+    fn shortest_path_between_unconnected_nodes() {
+        let network = detour_network();
+        let from = node_at(&network, [1000.0, 0.0]);
+        let to = node_at(&network, [6000.0, 5000.0]);
+
+        assert!(network.shortest_path(from, to).is_none());
+        assert_eq!(network.shortest_path(from, from).unwrap().points, vec![[1000.0, 0.0]]);
+    }
+
+    #[test]
+    fn nearest_road_point() {
+        let network = detour_network();
+
+        let point = network.nearest_road_point(2000.0, -50.0).unwrap();
+        assert_eq!(point.position, [2000.0, 0.0]);
+        assert_eq!(point.distance, 50.0);
+        assert_eq!(network.edges[point.edge].road, Some(0));
+
+        // the connections through the safe zone aren't roads
+        let point = network.nearest_road_point(0.0, 0.0).unwrap();
+        assert!(network.edges[point.edge].road.is_some());
+        assert!(RoadNetwork::create(&[], &safe_zone()).nearest_road_point(0.0, 0.0).is_none());
+    }
+}
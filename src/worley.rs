@@ -0,0 +1,166 @@
+use crate::random::Random;
+
+// distances are in cell units, one feature point per cell
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WorleySample {
+    // distance to the nearest feature point
+    pub f1: f32,
+    // distance to the second nearest feature point
+    pub f2: f32,
+    // f2 - f1, zero along the borders between cells
+    pub edge: f32,
+    // stable for every point that shares the same nearest feature point
+    pub cell_id: u32,
+    pub feature_point: [f32; 2],
+}
+
+pub struct Worley {
+    seed: u32,
+    // how far feature points can stray from the middle of their cell, 0 is a grid and 1 is fully random
+    pub jitter: f32,
+}
+
+impl Worley {
+    pub fn create(random: &mut Random) -> Self {
+        let mut worley = Worley::default();
+        worley.seed(random);
+        return worley;
+    }
+
+    pub fn seed(&mut self, random: &mut Random) {
+        self.seed = random.next_u32();
+    }
+
+    // with more than half a cell of jitter the nearest points can be two cells away, so 5x5 cells are searched
+    pub fn sample(&self, x: f32, y: f32) -> WorleySample {
+        let cell_x = x.floor() as i32;
+        let cell_y = y.floor() as i32;
+        let radius = if self.jitter > 0.5 { 2 } else { 1 };
+
+        let mut f1 = f32::INFINITY;
+        let mut f2 = f32::INFINITY;
+        let mut cell_id = 0;
+        let mut feature_point = [x, y];
+        for offset_x in -radius..=radius {
+            for offset_y in -radius..=radius {
+                let hash = self.hash(cell_x + offset_x, cell_y + offset_y);
+                let point = self.feature_point(cell_x + offset_x, cell_y + offset_y, hash);
+
+                let dx = point[0] - x;
+                let dy = point[1] - y;
+                let distance = (dx * dx + dy * dy).sqrt();
+                if distance < f1 {
+                    f2 = f1;
+                    f1 = distance;
+                    cell_id = hash;
+                    feature_point = point;
+                } else if distance < f2 {
+                    f2 = distance;
+                }
+            }
+        }
+
+        return WorleySample {
+            f1,
+            f2,
+            edge: f2 - f1,
+            cell_id,
+            feature_point,
+        };
+    }
+
+    fn feature_point(&self, cell_x: i32, cell_y: i32, hash: u32) -> [f32; 2] {
+        let random_x = (hash & 0xffff) as f32 / 65536.0;
+        let random_y = (hash >> 16) as f32 / 65536.0;
+
+        return [
+            cell_x as f32 + 0.5 + (random_x - 0.5) * self.jitter,
+            cell_y as f32 + 0.5 + (random_y - 0.5) * self.jitter,
+        ];
+    }
+
+    fn hash(&self, x: i32, y: i32) -> u32 {
+        let mut hash = self.seed ^ (x as u32).wrapping_mul(0x27d4eb2d) ^ (y as u32).wrapping_mul(0x165667b1);
+        hash = (hash ^ (hash >> 15)).wrapping_mul(0x2c1b3c6d);
+        hash = (hash ^ (hash >> 12)).wrapping_mul(0x297a2d39);
+        return hash ^ (hash >> 15);
+    }
+}
+
+impl Default for Worley {
+    fn default() -> Self {
+        return Worley {
+            seed: 0,
+            jitter: 1.0,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_values() {
+        let worley = Worley::create(&mut Random::create(1));
+        let other = Worley::create(&mut Random::create(1));
+        let different = Worley::create(&mut Random::create(2));
+
+        assert_eq!(worley.sample(3.3, -7.1), other.sample(3.3, -7.1));
+        assert_ne!(worley.sample(3.3, -7.1).feature_point, different.sample(3.3, -7.1).feature_point);
+    }
+
+    #[test]
+    fn distances_are_ordered() {
+        let worley = Worley::create(&mut Random::create(3));
+        for index in 0..200 {
+            let sample = worley.sample(index as f32 * 0.37, index as f32 * -0.21);
+
+            assert!(sample.f1 <= sample.f2);
+            assert_eq!(sample.edge, sample.f2 - sample.f1);
+        }
+    }
+
+    #[test]
+    fn feature_point_is_the_sample_with_zero_f1() {
+        let worley = Worley::create(&mut Random::create(4));
+        let sample = worley.sample(10.5, 20.5);
+        let at_feature = worley.sample(sample.feature_point[0], sample.feature_point[1]);
+
+        assert!(at_feature.f1 < 0.0001);
+        assert_eq!(at_feature.cell_id, sample.cell_id);
+    }
+
+    #[test]
+    fn full_jitter_finds_the_nearest_points() {
+        let worley = Worley::create(&mut Random::create(6));
+        // close to the cell corners is where feature points two cells away can be the nearest
+        for index in 0..40000 {
+            let x = (index % 200) as f32 + 0.02;
+            let y = (index / 200) as f32 + 0.02;
+
+            let mut distances = Vec::new();
+            for cell_x in x.floor() as i32 - 3..=x.floor() as i32 + 3 {
+                for cell_y in y.floor() as i32 - 3..=y.floor() as i32 + 3 {
+                    let point = worley.feature_point(cell_x, cell_y, worley.hash(cell_x, cell_y));
+                    distances.push(((point[0] - x).powi(2) + (point[1] - y).powi(2)).sqrt());
+                }
+            }
+            distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            let sample = worley.sample(x, y);
+            assert_eq!(sample.f1, distances[0]);
+            assert_eq!(sample.f2, distances[1]);
+        }
+    }
+
+    #[test]
+    fn no_jitter_is_a_grid() {
+        let mut worley = Worley::create(&mut Random::create(5));
+        worley.jitter = 0.0;
+
+        let sample = worley.sample(4.2, -3.9);
+        assert_eq!(sample.feature_point, [4.5, -3.5]);
+        assert!((sample.f1 - (0.3f32 * 0.3 + 0.4 * 0.4).sqrt()).abs() < 0.0001);
+    }
+}